@@ -0,0 +1,232 @@
+use rand::prelude::Distribution;
+use rand::SeedableRng;
+
+const SERVE_DELAY: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Perfect,
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "perfect" => Ok(Difficulty::Perfect),
+            _ => Err(format!(
+                "unknown difficulty {}, expected easy, normal, hard or perfect",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    pub reaction_delay: f32,
+    // Fraction of the paddle speed the AI is allowed to use.
+    pub speed: f32,
+    pub aim_error: f32,
+}
+
+impl Difficulty {
+    pub fn tuning(&self) -> Tuning {
+        match self {
+            Difficulty::Easy => Tuning {
+                reaction_delay: 0.4,
+                speed: 0.5,
+                aim_error: 2.0,
+            },
+            Difficulty::Normal => Tuning {
+                reaction_delay: 0.25,
+                speed: 0.7,
+                aim_error: 1.5,
+            },
+            Difficulty::Hard => Tuning {
+                reaction_delay: 0.12,
+                speed: 0.9,
+                aim_error: 1.25,
+            },
+            Difficulty::Perfect => Tuning {
+                reaction_delay: 0.0,
+                speed: 1.0,
+                aim_error: 0.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Ai {
+    tuning: Tuning,
+    rng: rand_chacha::ChaCha8Rng,
+    approaching: bool,
+    reaction: f32,
+    target: Option<f32>,
+    aim_error: f32,
+    speed_budget: f32,
+    serve_delay: f32,
+}
+
+impl Ai {
+    pub fn new(tuning: Tuning, seed: u64) -> Self {
+        Self {
+            tuning,
+            rng: rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+            approaching: false,
+            reaction: 0.0,
+            target: None,
+            aim_error: 0.0,
+            speed_budget: 0.0,
+            serve_delay: SERVE_DELAY,
+        }
+    }
+
+    // Follows the ball along its current heading until it reaches the face of
+    // the paddle, folding the path back into the arena at each side it
+    // passes. Positions are along the paddle's side.
+    pub fn predict_intercept(
+        player: usize,
+        ball: &crate::pong::Ball,
+        controller: &crate::pong::Controller,
+    ) -> f32 {
+        let axis = controller.players[player].side.axis();
+        let (_, length) = axis.split(Ai::arena(controller));
+        let time = Ai::path_length(player, ball, controller);
+
+        let range = length - ball.size;
+        let (_, position) = axis.split(ball.position);
+        let (_, direction) = axis.split(ball.direction);
+        let y = (position + direction * time).rem_euclid(2.0 * range);
+        let y = if y > range { 2.0 * range - y } else { y };
+
+        y + ball.size / 2.0
+    }
+
+    fn arena(controller: &crate::pong::Controller) -> crate::pong::Vector2D {
+        let size = controller.size();
+        crate::pong::Vector2D::new(size.width as f32, size.height as f32)
+    }
+
+    // Distance the ball travels along its heading to reach the paddle face.
+    fn path_length(
+        player: usize,
+        ball: &crate::pong::Ball,
+        controller: &crate::pong::Controller,
+    ) -> f32 {
+        let paddle = &controller.players[player];
+        let axis = paddle.side.axis();
+        let (normal, _) = axis.split(paddle.side.normal());
+        let (ball_position, _) = axis.split(ball.position);
+        let (paddle_position, _) = axis.split(paddle.position);
+        let (depth, _) = axis.split(crate::pong::Vector2D::new(paddle.width, paddle.height));
+        let distance = if normal > 0.0 {
+            ball_position - (paddle_position + depth)
+        } else {
+            paddle_position - (ball_position + ball.size)
+        };
+        distance.max(0.0) / axis.split(ball.direction).0.abs()
+    }
+
+    // The approaching ball that reaches the paddle first, if any.
+    pub fn incoming(
+        player: usize,
+        controller: &crate::pong::Controller,
+    ) -> Option<&crate::pong::Ball> {
+        if controller.state() != crate::rules::MatchState::Rally {
+            return None;
+        }
+
+        let side = controller.players[player].side;
+        let (normal, _) = side.axis().split(side.normal());
+        let arrival =
+            |ball: &crate::pong::Ball| Ai::path_length(player, ball, controller) / ball.speed;
+        controller
+            .balls
+            .iter()
+            .filter(|ball| side.axis().split(ball.direction).0 * normal < 0.0)
+            .min_by(|a, b| arrival(a).total_cmp(&arrival(b)))
+    }
+}
+
+impl crate::controls::PaddleControl for Ai {
+    fn input(
+        &mut self,
+        player: usize,
+        controller: &crate::pong::Controller,
+        dt: f32,
+    ) -> crate::pong::Input {
+        let incoming = Ai::incoming(player, controller);
+        let approaching = incoming.is_some();
+        if approaching != self.approaching {
+            self.approaching = approaching;
+            self.reaction = self.tuning.reaction_delay;
+            self.target = None;
+        }
+
+        if self.reaction > 0.0 {
+            self.reaction -= dt;
+        } else if self.target.is_none() {
+            self.target = Some(if let Some(ball) = incoming {
+                let error = self.tuning.aim_error * controller.players[player].length() / 2.0;
+                self.aim_error = if error > 0.0 {
+                    rand::distributions::Uniform::new_inclusive(-error, error).sample(&mut self.rng)
+                } else {
+                    0.0
+                };
+                Ai::predict_intercept(player, ball, controller) + self.aim_error
+            } else {
+                let axis = controller.players[player].side.axis();
+                axis.split(Ai::arena(controller)).1 / 2.0
+            });
+        } else if let Some(ball) = incoming {
+            // A curving ball drifts off any straight prediction, and with
+            // several balls the one arriving first can change, so keep
+            // following them.
+            if ball.spin != 0.0 || controller.balls.len() > 1 {
+                self.target =
+                    Some(Ai::predict_intercept(player, ball, controller) + self.aim_error);
+            }
+        }
+
+        let step = controller.config().paddle_speed * dt;
+        self.speed_budget = (self.speed_budget + self.tuning.speed * step).min(step);
+
+        let Some(target) = self.target else {
+            return crate::pong::Input::None;
+        };
+        if self.speed_budget < step {
+            return crate::pong::Input::None;
+        }
+
+        let paddle = &controller.players[player];
+        let (_, center) = paddle.side.axis().split(paddle.center());
+        if (target - center).abs() < step {
+            crate::pong::Input::None
+        } else {
+            self.speed_budget -= step;
+            if target > center {
+                crate::pong::Input::Up
+            } else {
+                crate::pong::Input::Down
+            }
+        }
+    }
+
+    fn serve(&mut self, _: usize, _: &crate::pong::Controller, dt: f32) -> bool {
+        self.serve_delay -= dt;
+        if self.serve_delay > 0.0 {
+            return false;
+        }
+
+        self.serve_delay = SERVE_DELAY;
+        true
+    }
+}
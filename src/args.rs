@@ -0,0 +1,326 @@
+pub const USAGE: &str = "usage: pong [OPTIONS]
+
+options:
+    --config FILE              gameplay settings, default pong.toml if present
+    --set KEY=VALUE            override a config key, like game.ball_speed=600
+    --seed N                   seed for all ball randomness
+    --tick-rate HZ             simulation ticks per second
+    --points N                 points needed to win a set
+    --win-by-one               do not require a two point lead
+    --best-of N                number of sets in a match
+    --serve RULE               alternate, loser or winner serves next
+    --players N                2 to 4 players, more than 2 adds paddles on
+                               the bottom and top and plays for lives
+    --lives N                  lives per player with more than 2 players
+    --p1 KIND                  keyboard, mouse, gamepad[:ID], easy, normal,
+                               hard or perfect, only the last four and by
+                               default normal in headless mode
+    --p2 KIND                  same as --p1
+    --p3 KIND                  same as --p1, for the bottom paddle
+    --p4 KIND                  same as --p1, for the top paddle
+    --window MODE              windowed, borderless or exclusive fullscreen
+    --vsync MODE               fifo, mailbox or immediate presentation
+    --replay FILE              play back a recorded match
+    --snapshot FILE            start from a saved snapshot
+    --net-peer ADDR            play against a peer at ADDR over UDP
+    --net-bind ADDR            local UDP address, default 0.0.0.0:7878
+    --net-player N             paddle controlled on this machine, 1 or 2
+    --net-delay TICKS          local input delay before rollback kicks in
+    --net-latency MS           add artificial latency to sent packets
+    --net-jitter MS            add up to MS of random extra latency
+    --net-loss PERCENT         drop this share of sent packets
+    --headless                 simulate without a window or GPU
+    --ticks N                  ticks to simulate in headless mode
+    --dt SECONDS               tick length, instead of --tick-rate
+    --size WIDTHxHEIGHT        arena size in game units, default 800x600
+    --level NAME|FILE          play a level file or a bundled level: pillars,
+                               gates or fortress
+    --screenshot FILE          render the final headless frame to a PNG
+    --save-snapshot FILE       save the final headless state, as json if
+                               FILE ends in .json and binary otherwise";
+
+#[derive(Debug)]
+pub struct Args {
+    pub headless: bool,
+    pub seed: Option<u64>,
+    pub ticks: u64,
+    pub dt: f32,
+    pub tick_rate: f32,
+    pub rules: pong::rules::Ruleset,
+    pub config: pong::config::Config,
+    pub players: [pong::controls::PlayerKind; pong::rules::MAX_PLAYERS],
+    pub arena: winit::dpi::PhysicalSize<u32>,
+    pub level: pong::level::Level,
+    pub screenshot: Option<std::path::PathBuf>,
+    pub replay: Option<std::path::PathBuf>,
+    pub snapshot: Option<std::path::PathBuf>,
+    pub save_snapshot: Option<std::path::PathBuf>,
+    pub net_peer: Option<std::net::SocketAddr>,
+    pub net_bind: std::net::SocketAddr,
+    pub net_player: usize,
+    pub net_delay: u64,
+    pub net_conditions: pong::net::Conditions,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            seed: None,
+            ticks: 60 * 60 * 120,
+            dt: 1.0 / pong::timestep::DEFAULT_TICK_RATE,
+            tick_rate: pong::timestep::DEFAULT_TICK_RATE,
+            rules: pong::rules::Ruleset::default(),
+            config: pong::config::Config::default(),
+            players: [pong::controls::PlayerKind::Keyboard; pong::rules::MAX_PLAYERS],
+            arena: winit::dpi::PhysicalSize::new(800, 600),
+            level: pong::level::Level::default(),
+            screenshot: None,
+            replay: None,
+            snapshot: None,
+            save_snapshot: None,
+            net_peer: None,
+            net_bind: std::net::SocketAddr::from(([0, 0, 0, 0], 7878)),
+            net_player: 0,
+            net_delay: pong::net::DEFAULT_INPUT_DELAY,
+            net_conditions: pong::net::Conditions::default(),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut config_path = None;
+        let mut overrides = Vec::new();
+        let mut window_mode = None;
+        let mut vsync = None;
+        let mut level = None;
+        let mut sized = false;
+        let mut timed = false;
+        let mut chosen = [false; pong::rules::MAX_PLAYERS];
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => parsed.headless = true,
+                "--config" => {
+                    config_path = Some(Args::value::<std::path::PathBuf>(&arg, args.next())?)
+                }
+                "--window" => window_mode = Some(Args::value(&arg, args.next())?),
+                "--vsync" => vsync = Some(Args::value(&arg, args.next())?),
+                "--set" => {
+                    let value: String = Args::value(&arg, args.next())?;
+                    let (key, value) = value
+                        .split_once('=')
+                        .ok_or_else(|| format!("{} expects KEY=VALUE, got {}", arg, value))?;
+                    overrides.push((key.to_string(), value.to_string()));
+                }
+                "--seed" => parsed.seed = Some(Args::value(&arg, args.next())?),
+                "--ticks" => parsed.ticks = Args::value(&arg, args.next())?,
+                // Both set the tick length, from either side.
+                "--dt" | "--tick-rate" => {
+                    if timed {
+                        return Err("--dt and --tick-rate cannot be combined".to_string());
+                    }
+                    let value: f32 = Args::value(&arg, args.next())?;
+                    if value <= 0.0 || !value.is_finite() || !(1.0 / value).is_finite() {
+                        return Err(format!("{} must be greater than zero", arg));
+                    }
+                    if arg == "--dt" {
+                        parsed.dt = value;
+                        parsed.tick_rate = 1.0 / value;
+                    } else {
+                        parsed.tick_rate = value;
+                        parsed.dt = 1.0 / value;
+                    }
+                    timed = true;
+                }
+                "--points" => {
+                    parsed.rules.points_to_win = Args::value(&arg, args.next())?;
+                    if parsed.rules.points_to_win == 0 {
+                        return Err(format!("{} must be at least 1", arg));
+                    }
+                }
+                "--win-by-one" => parsed.rules.win_by_two = false,
+                "--best-of" => {
+                    parsed.rules.best_of = Args::value(&arg, args.next())?;
+                    if parsed.rules.best_of == 0 {
+                        return Err(format!("{} must be at least 1", arg));
+                    }
+                }
+                "--serve" => parsed.rules.serve = Args::value(&arg, args.next())?,
+                "--players" => {
+                    parsed.rules.players = Args::value(&arg, args.next())?;
+                    if !(2..=pong::rules::MAX_PLAYERS).contains(&parsed.rules.players) {
+                        return Err(format!(
+                            "{} must be between 2 and {}",
+                            arg,
+                            pong::rules::MAX_PLAYERS
+                        ));
+                    }
+                }
+                "--lives" => {
+                    parsed.rules.lives = Args::value(&arg, args.next())?;
+                    if parsed.rules.lives == 0 {
+                        return Err(format!("{} must be at least 1", arg));
+                    }
+                }
+                "--p1" | "--p2" | "--p3" | "--p4" => {
+                    let player = usize::from(arg.as_bytes()[3] - b'1');
+                    parsed.players[player] = Args::value(&arg, args.next())?;
+                    chosen[player] = true;
+                }
+                "--screenshot" => parsed.screenshot = Some(Args::value(&arg, args.next())?),
+                "--replay" => parsed.replay = Some(Args::value(&arg, args.next())?),
+                "--snapshot" => parsed.snapshot = Some(Args::value(&arg, args.next())?),
+                "--save-snapshot" => parsed.save_snapshot = Some(Args::value(&arg, args.next())?),
+                "--net-peer" => parsed.net_peer = Some(Args::value(&arg, args.next())?),
+                "--net-bind" => parsed.net_bind = Args::value(&arg, args.next())?,
+                "--net-player" => {
+                    parsed.net_player = match Args::value(&arg, args.next())? {
+                        1 => 0,
+                        2 => 1,
+                        _ => return Err(format!("{} must be 1 or 2", arg)),
+                    }
+                }
+                "--net-delay" => {
+                    parsed.net_delay = Args::value(&arg, args.next())?;
+                    if parsed.net_delay > pong::net::MAX_INPUT_DELAY {
+                        return Err(format!(
+                            "{} must be at most {}",
+                            arg,
+                            pong::net::MAX_INPUT_DELAY
+                        ));
+                    }
+                }
+                "--net-latency" => {
+                    parsed.net_conditions.latency =
+                        std::time::Duration::from_millis(Args::value(&arg, args.next())?)
+                }
+                "--net-jitter" => {
+                    parsed.net_conditions.jitter =
+                        std::time::Duration::from_millis(Args::value(&arg, args.next())?)
+                }
+                "--net-loss" => {
+                    let loss: f32 = Args::value(&arg, args.next())?;
+                    if !(0.0..=100.0).contains(&loss) {
+                        return Err(format!("{} must be between 0 and 100", arg));
+                    }
+                    parsed.net_conditions.loss = loss / 100.0;
+                }
+                "--size" => {
+                    let value: String = Args::value(&arg, args.next())?;
+                    let (width, height) = value
+                        .split_once('x')
+                        .ok_or_else(|| format!("{} expects WIDTHxHEIGHT, got {}", arg, value))?;
+                    parsed.arena.width = Args::value(&arg, Some(width.to_string()))?;
+                    parsed.arena.height = Args::value(&arg, Some(height.to_string()))?;
+                    if parsed.arena.width == 0 || parsed.arena.height == 0 {
+                        return Err(format!("{} must not be empty", arg));
+                    }
+                    sized = true;
+                }
+                "--level" => {
+                    let name: String = Args::value(&arg, args.next())?;
+                    level = Some(
+                        pong::level::Level::find(&name).map_err(|e| format!("{}: {}", name, e))?,
+                    );
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+
+        // An explicit config file must exist, the default one is optional.
+        let config_path = config_path.or_else(|| {
+            let path = std::path::PathBuf::from(pong::config::DEFAULT_PATH);
+            path.exists().then_some(path)
+        });
+        if let Some(path) = config_path {
+            parsed.config = pong::config::Config::load(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        if let Some(mode) = window_mode {
+            parsed.config.display.mode = mode;
+        }
+        if let Some(vsync) = vsync {
+            parsed.config.display.vsync = vsync;
+        }
+        for (key, value) in overrides {
+            parsed.config.set(&key, &value).map_err(|e| e.to_string())?;
+        }
+        // Levels bring their own arena size.
+        match level {
+            Some(_) if sized => return Err("--size cannot be combined with --level".to_string()),
+            Some(level) => {
+                parsed.arena = level.size();
+                parsed.level = level;
+            }
+            None => {
+                parsed.level.width = parsed.arena.width;
+                parsed.level.height = parsed.arena.height;
+            }
+        }
+        parsed
+            .config
+            .game
+            .validate_arena(&parsed.arena, parsed.rules.players)
+            .map_err(|e| e.to_string())?;
+        parsed
+            .level
+            .validate(&parsed.config.game, parsed.rules.players)
+            .map_err(|e| e.to_string())?;
+
+        // Nothing sends input without a window, so headless paddles are
+        // played by the AI.
+        if parsed.headless {
+            for (player, kind) in parsed.players.iter_mut().enumerate() {
+                if !chosen[player] {
+                    *kind = pong::controls::PlayerKind::Ai(pong::ai::Difficulty::Normal);
+                } else if kind.is_human() {
+                    return Err(format!(
+                        "--p{} must be easy, normal, hard or perfect in headless mode",
+                        player + 1
+                    ));
+                }
+            }
+        }
+        // Without gamepad support the paddle would never move.
+        if !cfg!(feature = "gamepad") {
+            let players = &parsed.players[..parsed.rules.players];
+            if let Some(player) = players
+                .iter()
+                .position(|kind| matches!(kind, pong::controls::PlayerKind::Gamepad(_)))
+            {
+                return Err(format!(
+                    "--p{} gamepad needs a build with the gamepad feature",
+                    player + 1
+                ));
+            }
+        }
+        if parsed.net_peer.is_some() && parsed.seed.is_none() {
+            return Err("--net-peer requires --seed so both peers play the same match".to_string());
+        }
+        if parsed.net_peer.is_some() && parsed.rules.players != 2 {
+            return Err("--net-peer only supports two players".to_string());
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn net(&self) -> Option<pong::net::NetOptions> {
+        Some(pong::net::NetOptions {
+            bind: self.net_bind,
+            peer: self.net_peer?,
+            player: self.net_player,
+            delay: self.net_delay,
+            conditions: self.net_conditions,
+        })
+    }
+
+    fn value<T: std::str::FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or_else(|| format!("{} expects a value", arg))?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid value for {}: {}", arg, value))
+    }
+}
@@ -0,0 +1,62 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quad {
+    pub rect: crate::collision::Aabb,
+    pub color: [f32; 3],
+}
+
+#[derive(Debug, Default)]
+pub struct QuadBatch {
+    quads: Vec<Quad>,
+}
+
+impl QuadBatch {
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+
+    pub fn push(&mut self, rect: crate::collision::Aabb, color: [f32; 3]) {
+        self.quads.push(Quad { rect, color });
+    }
+
+    pub fn extend(
+        &mut self,
+        rects: impl IntoIterator<Item = crate::collision::Aabb>,
+        color: [f32; 3],
+    ) {
+        self.quads
+            .extend(rects.into_iter().map(|rect| Quad { rect, color }));
+    }
+
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    // Vertices stay in arena units, the vertex shader maps them to the surface.
+    pub fn vertices(&self) -> Vec<crate::vertex::Vertex> {
+        self.quads
+            .iter()
+            .flat_map(|Quad { rect, color }| {
+                [
+                    [rect.x, rect.y],
+                    [rect.x + rect.width, rect.y],
+                    [rect.x + rect.width, rect.y + rect.height],
+                    [rect.x, rect.y + rect.height],
+                ]
+                .map(|[x, y]| crate::vertex::Vertex {
+                    position: [x, y, 0.0],
+                    color: *color,
+                })
+            })
+            .collect()
+    }
+
+    pub fn indices(capacity: usize) -> Vec<u32> {
+        (0..capacity as u32)
+            .flat_map(|i| [i * 4, i * 4 + 1, i * 4 + 2, i * 4, i * 4 + 2, i * 4 + 3])
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use winit::keyboard::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    P1Up,
+    P1Down,
+    P2Up,
+    P2Down,
+    P3Left,
+    P3Right,
+    P4Left,
+    P4Right,
+    Serve,
+    Pause,
+    Quit,
+    Screenshot,
+    QuickSave,
+    QuickLoad,
+    ReplayStep,
+    ReplayFaster,
+    ReplaySlower,
+}
+
+impl Action {
+    // Paddles on the bottom and top move right for up and left for down.
+    pub fn paddle(player: usize) -> (Action, Action) {
+        match player {
+            0 => (Action::P1Up, Action::P1Down),
+            1 => (Action::P2Up, Action::P2Down),
+            2 => (Action::P3Right, Action::P3Left),
+            _ => (Action::P4Right, Action::P4Left),
+        }
+    }
+}
+
+// Keys use winit's physical key names, like "KeyW" or "ArrowUp", so a
+// binding stays on the same spot of the keyboard whatever the layout.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    pub p1_up: Vec<KeyCode>,
+    pub p1_down: Vec<KeyCode>,
+    pub p2_up: Vec<KeyCode>,
+    pub p2_down: Vec<KeyCode>,
+    pub p3_left: Vec<KeyCode>,
+    pub p3_right: Vec<KeyCode>,
+    pub p4_left: Vec<KeyCode>,
+    pub p4_right: Vec<KeyCode>,
+    pub serve: Vec<KeyCode>,
+    pub pause: Vec<KeyCode>,
+    pub quit: Vec<KeyCode>,
+    pub screenshot: Vec<KeyCode>,
+    pub quick_save: Vec<KeyCode>,
+    pub quick_load: Vec<KeyCode>,
+    pub replay_step: Vec<KeyCode>,
+    pub replay_faster: Vec<KeyCode>,
+    pub replay_slower: Vec<KeyCode>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            p1_up: vec![KeyCode::KeyW],
+            p1_down: vec![KeyCode::KeyS],
+            p2_up: vec![KeyCode::ArrowUp],
+            p2_down: vec![KeyCode::ArrowDown],
+            p3_left: vec![KeyCode::KeyJ],
+            p3_right: vec![KeyCode::KeyL],
+            p4_left: vec![KeyCode::Numpad4],
+            p4_right: vec![KeyCode::Numpad6],
+            serve: vec![KeyCode::Space],
+            pause: vec![KeyCode::KeyP, KeyCode::Pause],
+            quit: vec![KeyCode::Escape],
+            screenshot: vec![KeyCode::F12],
+            quick_save: vec![KeyCode::F5],
+            quick_load: vec![KeyCode::F9],
+            replay_step: vec![KeyCode::Period, KeyCode::ArrowRight],
+            replay_faster: vec![KeyCode::Equal, KeyCode::NumpadAdd],
+            replay_slower: vec![KeyCode::Minus, KeyCode::NumpadSubtract],
+        }
+    }
+}
+
+impl Bindings {
+    fn table(&self) -> [(Action, &[KeyCode]); 17] {
+        [
+            (Action::P1Up, &self.p1_up),
+            (Action::P1Down, &self.p1_down),
+            (Action::P2Up, &self.p2_up),
+            (Action::P2Down, &self.p2_down),
+            (Action::P3Left, &self.p3_left),
+            (Action::P3Right, &self.p3_right),
+            (Action::P4Left, &self.p4_left),
+            (Action::P4Right, &self.p4_right),
+            (Action::Serve, &self.serve),
+            (Action::Pause, &self.pause),
+            (Action::Quit, &self.quit),
+            (Action::Screenshot, &self.screenshot),
+            (Action::QuickSave, &self.quick_save),
+            (Action::QuickLoad, &self.quick_load),
+            (Action::ReplayStep, &self.replay_step),
+            (Action::ReplayFaster, &self.replay_faster),
+            (Action::ReplaySlower, &self.replay_slower),
+        ]
+    }
+
+    // A key may be bound to several actions, like a shared key for serving
+    // and pausing a replay.
+    pub fn actions(&self, key: KeyCode) -> Vec<Action> {
+        self.table()
+            .into_iter()
+            .filter(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| action)
+            .collect()
+    }
+
+    pub fn is_bound(&self, key: KeyCode, action: Action) -> bool {
+        self.table()
+            .into_iter()
+            .any(|(bound, keys)| bound == action && keys.contains(&key))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.table()
+            .into_iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+}
+
+// Short name of a key for on-screen prompts, like W for KeyW.
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_uppercase()
+}
@@ -0,0 +1,175 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub time: f32,
+    pub normal: crate::pong::Vector2D,
+}
+
+impl Aabb {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.x < other.x + other.width
+            && self.x + self.width > other.x
+            && self.y < other.y + other.height
+            && self.y + self.height > other.y
+    }
+
+    // Sweeps `self` along `displacement` and returns the fraction of the
+    // movement (0..=1) at which it first touches `target`, along with the
+    // surface normal of `target` at the contact. Boxes that already overlap
+    // or only slide along each other are not reported.
+    pub fn sweep(&self, displacement: &crate::pong::Vector2D, target: &Aabb) -> Option<Hit> {
+        let expanded = Aabb::new(
+            target.x - self.width,
+            target.y - self.height,
+            target.width + self.width,
+            target.height + self.height,
+        );
+
+        let mut entry = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        let mut normal = crate::pong::Vector2D::new(0.0, 0.0);
+
+        for (origin, delta, min, max, axis) in [
+            (
+                self.x,
+                displacement.x,
+                expanded.x,
+                expanded.x + expanded.width,
+                0,
+            ),
+            (
+                self.y,
+                displacement.y,
+                expanded.y,
+                expanded.y + expanded.height,
+                1,
+            ),
+        ] {
+            if delta == 0.0 {
+                if origin <= min || origin >= max {
+                    return None;
+                }
+                continue;
+            }
+
+            let t1 = (min - origin) / delta;
+            let t2 = (max - origin) / delta;
+            let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+
+            if near > entry {
+                entry = near;
+                normal = if axis == 0 {
+                    crate::pong::Vector2D::new(-delta.signum(), 0.0)
+                } else {
+                    crate::pong::Vector2D::new(0.0, -delta.signum())
+                };
+            }
+            exit = exit.min(far);
+        }
+
+        if entry >= exit || !(0.0..=1.0).contains(&entry) {
+            return None;
+        }
+
+        Some(Hit {
+            time: entry,
+            normal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pong::Vector2D;
+
+    #[test]
+    fn sweep_reports_entry_time_and_normal() {
+        let ball = Aabb::new(0.0, 0.0, 10.0, 10.0);
+        let target = Aabb::new(50.0, -5.0, 10.0, 20.0);
+
+        let hit = ball.sweep(&Vector2D::new(100.0, 0.0), &target).unwrap();
+        assert_eq!(hit.time, 0.4);
+        assert_eq!(hit.normal, Vector2D::new(-1.0, 0.0));
+
+        let hit = Aabb::new(90.0, 0.0, 10.0, 10.0)
+            .sweep(&Vector2D::new(-100.0, 0.0), &target)
+            .unwrap();
+        assert_eq!(hit.time, 0.3);
+        assert_eq!(hit.normal, Vector2D::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn sweep_catches_thin_targets_at_high_speed() {
+        // Ten times the default ball speed over four 30 Hz ticks jumps far
+        // past a paddle, which a per-tick overlap test would never see.
+        let ball = Aabb::new(400.0, 290.0, 20.0, 20.0);
+        let paddle = Aabb::new(0.0, 250.0, 30.0, 100.0);
+        let displacement = Vector2D::new(-4000.0 / 30.0 * 4.0, 0.0);
+
+        let end = Aabb::new(400.0 + displacement.x, 290.0, 20.0, 20.0);
+        assert!(!end.overlaps(&paddle));
+
+        let hit = ball.sweep(&displacement, &paddle).unwrap();
+        assert!((0.0..1.0).contains(&hit.time));
+        assert_eq!(hit.normal, Vector2D::new(1.0, 0.0));
+        assert_eq!(400.0 + displacement.x * hit.time, 30.0);
+    }
+
+    #[test]
+    fn sweep_reports_vertical_hits() {
+        let ball = Aabb::new(0.0, 0.0, 10.0, 10.0);
+        let wall = Aabb::new(-100.0, 100.0, 300.0, 50.0);
+
+        let hit = ball.sweep(&Vector2D::new(20.0, 180.0), &wall).unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vector2D::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn sweep_ignores_misses() {
+        let ball = Aabb::new(0.0, 0.0, 10.0, 10.0);
+        let target = Aabb::new(50.0, 0.0, 10.0, 10.0);
+
+        // Out of reach, moving away, off to the side and sliding along it.
+        assert!(ball.sweep(&Vector2D::new(30.0, 0.0), &target).is_none());
+        assert!(ball.sweep(&Vector2D::new(-100.0, 0.0), &target).is_none());
+        assert!(ball
+            .sweep(
+                &Vector2D::new(100.0, 0.0),
+                &Aabb::new(50.0, 20.0, 10.0, 10.0)
+            )
+            .is_none());
+        assert!(ball
+            .sweep(
+                &Vector2D::new(100.0, 0.0),
+                &Aabb::new(50.0, 10.0, 10.0, 10.0)
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn sweep_ignores_overlapping_boxes() {
+        let ball = Aabb::new(0.0, 0.0, 10.0, 10.0);
+        let target = Aabb::new(5.0, 5.0, 10.0, 10.0);
+
+        assert!(ball.overlaps(&target));
+        assert!(ball.sweep(&Vector2D::new(100.0, 0.0), &target).is_none());
+    }
+}
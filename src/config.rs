@@ -0,0 +1,383 @@
+pub const DEFAULT_PATH: &str = "pong.toml";
+pub const MAX_BALLS: u32 = 16;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    UnknownKey(String),
+    Invalid { key: String, reason: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "config i/o error: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::UnknownKey(key) => write!(f, "unknown config key {}", key),
+            ConfigError::Invalid { key, reason } => write!(f, "invalid {}: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+// How the ball leaves a paddle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BounceModel {
+    // A random angle from the zone of the paddle that was hit.
+    Zones,
+    // An angle set by the contact offset, plus spin from the paddle's motion.
+    #[default]
+    Spin,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub paddle_width: f32,
+    pub paddle_height: f32,
+    pub paddle_speed: f32,
+    // Fastest a paddle may move, reached by analog sticks at full tilt and
+    // by mouse control. Also caps `paddle_speed`.
+    pub paddle_max_speed: f32,
+    pub ball_size: f32,
+    // Speed of a served ball.
+    pub ball_speed: f32,
+    // Factor the ball speed is multiplied by on every paddle hit.
+    pub ball_speedup: f32,
+    // Cap on the speed reached through `ball_speedup`.
+    pub ball_max_speed: f32,
+    pub bounce: BounceModel,
+    // Share of the paddle height, around its center, that returns the ball
+    // almost flat. Hits outside it are deflected up or down. Zones only.
+    pub bounce_center: f32,
+    // Angle from the horizontal, in degrees, of a ball hit by a paddle edge.
+    // Spin never turns the ball steeper than this either.
+    pub max_bounce_angle: f32,
+    // Spin given to the ball per unit of paddle velocity.
+    pub spin_transfer: f32,
+    // Share of its spin the ball loses every second.
+    pub spin_decay: f32,
+    // Share of its spin a ball trades for horizontal speed at a wall.
+    pub wall_grip: f32,
+    // Balls allowed in play at once. Above one, extra balls join a rally
+    // after `multiball_hits` paddle hits or `multiball_interval` seconds,
+    // whichever is set and comes first.
+    pub max_balls: u32,
+    pub multiball_hits: u32,
+    pub multiball_interval: f32,
+    // Whether balls in play bounce off each other.
+    pub ball_collisions: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            paddle_width: 30.0,
+            paddle_height: 100.0,
+            paddle_speed: 500.0,
+            paddle_max_speed: 500.0,
+            ball_size: 20.0,
+            ball_speed: 400.0,
+            ball_speedup: 1.05,
+            ball_max_speed: 900.0,
+            bounce: BounceModel::default(),
+            bounce_center: 0.3,
+            max_bounce_angle: 60.0,
+            spin_transfer: 0.001,
+            spin_decay: 0.5,
+            wall_grip: 0.3,
+            max_balls: 1,
+            multiball_hits: 0,
+            multiball_interval: 0.0,
+            ball_collisions: false,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let positive = [
+            ("game.paddle_width", self.paddle_width),
+            ("game.paddle_height", self.paddle_height),
+            ("game.paddle_speed", self.paddle_speed),
+            ("game.paddle_max_speed", self.paddle_max_speed),
+            ("game.ball_size", self.ball_size),
+            ("game.ball_speed", self.ball_speed),
+            ("game.ball_max_speed", self.ball_max_speed),
+        ];
+        for (key, value) in positive {
+            if value <= 0.0 || !value.is_finite() {
+                return Err(ConfigError::Invalid {
+                    key: key.to_string(),
+                    reason: format!("must be greater than zero, got {}", value),
+                });
+            }
+        }
+
+        let non_negative = [
+            ("game.spin_transfer", self.spin_transfer),
+            ("game.spin_decay", self.spin_decay),
+            ("game.multiball_interval", self.multiball_interval),
+        ];
+        for (key, value) in non_negative {
+            if value < 0.0 || !value.is_finite() {
+                return Err(ConfigError::Invalid {
+                    key: key.to_string(),
+                    reason: format!("must not be negative, got {}", value),
+                });
+            }
+        }
+
+        if !(1.0..=2.0).contains(&self.ball_speedup) {
+            return Err(ConfigError::Invalid {
+                key: "game.ball_speedup".to_string(),
+                reason: format!("must be between 1 and 2, got {}", self.ball_speedup),
+            });
+        }
+        if self.ball_max_speed < self.ball_speed {
+            return Err(ConfigError::Invalid {
+                key: "game.ball_max_speed".to_string(),
+                reason: format!(
+                    "must not be below game.ball_speed {}, got {}",
+                    self.ball_speed, self.ball_max_speed
+                ),
+            });
+        }
+
+        if !(1..=MAX_BALLS).contains(&self.max_balls) {
+            return Err(ConfigError::Invalid {
+                key: "game.max_balls".to_string(),
+                reason: format!(
+                    "must be between 1 and {}, got {}",
+                    MAX_BALLS, self.max_balls
+                ),
+            });
+        }
+
+        let fractions = [
+            ("game.bounce_center", self.bounce_center),
+            ("game.wall_grip", self.wall_grip),
+        ];
+        for (key, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(ConfigError::Invalid {
+                    key: key.to_string(),
+                    reason: format!("must be between 0 and 1, got {}", value),
+                });
+            }
+        }
+
+        if !(self.max_bounce_angle > 0.0 && self.max_bounce_angle < 90.0) {
+            return Err(ConfigError::Invalid {
+                key: "game.max_bounce_angle".to_string(),
+                reason: format!(
+                    "must be between 0 and 90 degrees, got {}",
+                    self.max_bounce_angle
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Checks that paddles and ball fit the arena the match is played in.
+    // Paddles on the bottom and top only come with more than two players.
+    pub fn validate_arena(
+        &self,
+        size: &winit::dpi::PhysicalSize<u32>,
+        players: usize,
+    ) -> Result<(), ConfigError> {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let (length, depth) = if players > 2 {
+            (width.min(height), width.min(height))
+        } else {
+            (height, width)
+        };
+        if self.paddle_height > length {
+            return Err(ConfigError::Invalid {
+                key: "game.paddle_height".to_string(),
+                reason: format!(
+                    "{} does not fit a {}x{} arena",
+                    self.paddle_height, width, height
+                ),
+            });
+        }
+        if self.ball_size > length || 2.0 * self.paddle_width + self.ball_size > depth {
+            return Err(ConfigError::Invalid {
+                key: "game.ball_size".to_string(),
+                reason: format!(
+                    "{} does not fit a {}x{} arena",
+                    self.ball_size, width, height
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Exclusive,
+}
+
+impl std::str::FromStr for WindowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "windowed" => Ok(WindowMode::Windowed),
+            "borderless" => Ok(WindowMode::Borderless),
+            "exclusive" => Ok(WindowMode::Exclusive),
+            _ => Err(format!(
+                "unknown window mode {}, expected windowed, borderless or exclusive",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresentMode {
+    #[default]
+    Fifo,
+    Mailbox,
+    Immediate,
+}
+
+impl std::str::FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(PresentMode::Fifo),
+            "mailbox" => Ok(PresentMode::Mailbox),
+            "immediate" => Ok(PresentMode::Immediate),
+            _ => Err(format!(
+                "unknown present mode {}, expected fifo, mailbox or immediate",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub mode: WindowMode,
+    pub vsync: PresentMode,
+}
+
+// How a paddle reacts while both its up and down keys are held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpposingKeys {
+    #[default]
+    LastPressed,
+    CancelOut,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub opposing_keys: OpposingKeys,
+    // Share of the stick's travel, from its center, that is ignored.
+    pub gamepad_deadzone: f32,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            opposing_keys: OpposingKeys::default(),
+            gamepad_deadzone: 0.2,
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..1.0).contains(&self.gamepad_deadzone) {
+            return Err(ConfigError::Invalid {
+                key: "input.gamepad_deadzone".to_string(),
+                reason: format!(
+                    "must be at least 0 and below 1, got {}",
+                    self.gamepad_deadzone
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub game: GameConfig,
+    pub display: DisplayConfig,
+    pub input: InputConfig,
+    pub bindings: crate::bindings::Bindings,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, ConfigError> {
+        Config::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.game.validate()?;
+        self.input.validate()
+    }
+
+    // Applies a `section.key=value` override. The value is read as a toml
+    // value, falling back to a plain string so `--set a.b=word` works.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let unknown = || ConfigError::UnknownKey(key.to_string());
+        let (section, field) = key.split_once('.').ok_or_else(unknown)?;
+
+        let value = match format!("value = {}", value).parse::<toml::Table>() {
+            Ok(mut table) => table.remove("value").unwrap(),
+            Err(_) => toml::Value::String(value.to_string()),
+        };
+
+        let mut table = toml::Table::try_from(&*self).unwrap();
+        let entry = table
+            .get_mut(section)
+            .and_then(|section| section.as_table_mut())
+            .and_then(|section| section.get_mut(field))
+            .ok_or_else(unknown)?;
+        *entry = value;
+
+        *self = table
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Invalid {
+                key: key.to_string(),
+                reason: e.message().to_string(),
+            })?;
+        self.validate()
+    }
+}
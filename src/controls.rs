@@ -0,0 +1,410 @@
+pub trait PaddleControl {
+    fn action(
+        &mut self,
+        _key: winit::keyboard::KeyCode,
+        _action: crate::bindings::Action,
+        _state: winit::event::ElementState,
+    ) {
+    }
+
+    // Called when key releases may have been missed, like when the window
+    // loses focus.
+    fn release_all(&mut self) {}
+
+    fn gamepad(&mut self, _event: &crate::gamepad::Event) {}
+
+    // Cursor position in arena units.
+    fn cursor(&mut self, _position: crate::pong::Vector2D) {}
+
+    fn input(
+        &mut self,
+        player: usize,
+        controller: &crate::pong::Controller,
+        dt: f32,
+    ) -> crate::pong::Input;
+
+    fn serve(&mut self, _player: usize, _controller: &crate::pong::Controller, _dt: f32) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct Keyboard {
+    up: crate::bindings::Action,
+    down: crate::bindings::Action,
+    opposing: crate::config::OpposingKeys,
+    // Every held key bound to this paddle, oldest press first.
+    held: Vec<(winit::keyboard::KeyCode, crate::pong::Input)>,
+}
+
+impl Keyboard {
+    pub fn new(
+        up: crate::bindings::Action,
+        down: crate::bindings::Action,
+        opposing: crate::config::OpposingKeys,
+    ) -> Self {
+        Self {
+            up,
+            down,
+            opposing,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn for_player(player: usize, opposing: crate::config::OpposingKeys) -> Self {
+        let (up, down) = crate::bindings::Action::paddle(player);
+        Keyboard::new(up, down, opposing)
+    }
+
+    pub fn current(&self) -> crate::pong::Input {
+        match self.opposing {
+            crate::config::OpposingKeys::LastPressed => self
+                .held
+                .last()
+                .map_or(crate::pong::Input::None, |(_, input)| *input),
+            crate::config::OpposingKeys::CancelOut => {
+                let up = self
+                    .held
+                    .iter()
+                    .any(|(_, input)| *input == crate::pong::Input::Up);
+                let down = self
+                    .held
+                    .iter()
+                    .any(|(_, input)| *input == crate::pong::Input::Down);
+                match (up, down) {
+                    (true, false) => crate::pong::Input::Up,
+                    (false, true) => crate::pong::Input::Down,
+                    _ => crate::pong::Input::None,
+                }
+            }
+        }
+    }
+}
+
+impl PaddleControl for Keyboard {
+    fn action(
+        &mut self,
+        key: winit::keyboard::KeyCode,
+        action: crate::bindings::Action,
+        state: winit::event::ElementState,
+    ) {
+        let input = if action == self.up {
+            crate::pong::Input::Up
+        } else if action == self.down {
+            crate::pong::Input::Down
+        } else {
+            return;
+        };
+
+        // Key repeat sends further presses of a held key, which must not move
+        // it to the back of the queue.
+        let held = self.held.iter().position(|held| *held == (key, input));
+        match (state, held) {
+            (winit::event::ElementState::Pressed, None) => self.held.push((key, input)),
+            (winit::event::ElementState::Released, Some(index)) => {
+                self.held.remove(index);
+            }
+            _ => (),
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.held.clear();
+    }
+
+    fn input(&mut self, _: usize, _: &crate::pong::Controller, _: f32) -> crate::pong::Input {
+        self.current()
+    }
+}
+
+// Paddles on the left and right follow the vertical stick and d-pad, and
+// those on the bottom and top the horizontal ones.
+#[derive(Debug)]
+pub struct Gamepad {
+    id: usize,
+    deadzone: f32,
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+    stick: crate::pong::Vector2D,
+}
+
+impl Gamepad {
+    pub fn new(id: usize, deadzone: f32) -> Self {
+        Self {
+            id,
+            deadzone,
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+            stick: crate::pong::Vector2D::new(0.0, 0.0),
+        }
+    }
+
+    pub fn current(&self, side: crate::pong::Side) -> crate::pong::Input {
+        let (positive, negative, stick) = match side.axis() {
+            crate::pong::Axis::Horizontal => (self.up, self.down, self.stick.y),
+            crate::pong::Axis::Vertical => (self.right, self.left, self.stick.x),
+        };
+
+        // The d-pad wins over the stick while it is held.
+        match (positive, negative) {
+            (true, false) => crate::pong::Input::Up,
+            (false, true) => crate::pong::Input::Down,
+            _ => {
+                match crate::pong::Input::axis(crate::gamepad::apply_deadzone(stick, self.deadzone))
+                {
+                    crate::pong::Input::Axis(0) => crate::pong::Input::None,
+                    input => input,
+                }
+            }
+        }
+    }
+}
+
+impl PaddleControl for Gamepad {
+    fn gamepad(&mut self, event: &crate::gamepad::Event) {
+        match *event {
+            crate::gamepad::Event::Button { id, button, state } if id == self.id => {
+                let pressed = state == winit::event::ElementState::Pressed;
+                match button {
+                    crate::gamepad::Button::DPadUp => self.up = pressed,
+                    crate::gamepad::Button::DPadDown => self.down = pressed,
+                    crate::gamepad::Button::DPadLeft => self.left = pressed,
+                    crate::gamepad::Button::DPadRight => self.right = pressed,
+                    _ => (),
+                }
+            }
+            crate::gamepad::Event::Axis { id, axis, value } if id == self.id => match axis {
+                crate::gamepad::Axis::LeftStickX => self.stick.x = value,
+                crate::gamepad::Axis::LeftStickY => self.stick.y = value,
+                crate::gamepad::Axis::Other => (),
+            },
+            crate::gamepad::Event::Disconnected(id) if id == self.id => {
+                *self = Gamepad::new(self.id, self.deadzone);
+            }
+            _ => (),
+        }
+    }
+
+    fn input(
+        &mut self,
+        player: usize,
+        controller: &crate::pong::Controller,
+        _: f32,
+    ) -> crate::pong::Input {
+        self.current(controller.players[player].side)
+    }
+}
+
+// Moves the paddle center towards the cursor, along the paddle's side.
+#[derive(Debug, Default)]
+pub struct Mouse {
+    target: Option<crate::pong::Vector2D>,
+}
+
+impl PaddleControl for Mouse {
+    fn cursor(&mut self, position: crate::pong::Vector2D) {
+        self.target = Some(position);
+    }
+
+    fn input(
+        &mut self,
+        player: usize,
+        controller: &crate::pong::Controller,
+        _: f32,
+    ) -> crate::pong::Input {
+        let axis = controller.players[player].side.axis();
+        self.target.map_or(crate::pong::Input::None, |target| {
+            crate::pong::Input::target(axis.split(target).1)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerKind {
+    Keyboard,
+    Mouse,
+    // Without an id, player N uses the Nth gamepad.
+    Gamepad(Option<usize>),
+    Ai(crate::ai::Difficulty),
+}
+
+impl PlayerKind {
+    pub fn is_human(&self) -> bool {
+        !matches!(self, PlayerKind::Ai(_))
+    }
+
+    // How this kind of player serves, for the on-screen prompts.
+    pub fn serve_prompt(&self, bindings: &crate::bindings::Bindings) -> Option<String> {
+        match self {
+            PlayerKind::Keyboard => bindings
+                .keys(crate::bindings::Action::Serve)
+                .first()
+                .map(|key| format!("PRESS {}", crate::bindings::key_name(*key))),
+            PlayerKind::Mouse => Some("CLICK".to_string()),
+            PlayerKind::Gamepad(_) => Some("PRESS A".to_string()),
+            PlayerKind::Ai(_) => None,
+        }
+    }
+
+    pub fn build(
+        &self,
+        player: usize,
+        seed: u64,
+        input: &crate::config::InputConfig,
+    ) -> Box<dyn PaddleControl> {
+        match self {
+            PlayerKind::Keyboard => Box::new(Keyboard::for_player(player, input.opposing_keys)),
+            PlayerKind::Mouse => Box::new(Mouse::default()),
+            PlayerKind::Gamepad(id) => {
+                Box::new(Gamepad::new(id.unwrap_or(player), input.gamepad_deadzone))
+            }
+            PlayerKind::Ai(difficulty) => Box::new(crate::ai::Ai::new(
+                difficulty.tuning(),
+                seed ^ player as u64,
+            )),
+        }
+    }
+}
+
+impl std::str::FromStr for PlayerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keyboard" => Ok(PlayerKind::Keyboard),
+            "mouse" => Ok(PlayerKind::Mouse),
+            "gamepad" => Ok(PlayerKind::Gamepad(None)),
+            _ => match s.strip_prefix("gamepad:") {
+                Some(id) => id
+                    .parse()
+                    .map(|id| PlayerKind::Gamepad(Some(id)))
+                    .map_err(|_| format!("invalid gamepad id {}", id)),
+                None => s.parse().map(PlayerKind::Ai),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::Action;
+    use winit::event::ElementState::{Pressed, Released};
+    use winit::keyboard::KeyCode;
+
+    fn keyboard(opposing: crate::config::OpposingKeys) -> Keyboard {
+        Keyboard::for_player(0, opposing)
+    }
+
+    fn send(keyboard: &mut Keyboard, keys: &[(KeyCode, winit::event::ElementState)]) {
+        for &(key, state) in keys {
+            let action = match key {
+                KeyCode::KeyW | KeyCode::ArrowUp => Action::P1Up,
+                KeyCode::KeyS | KeyCode::ArrowDown => Action::P1Down,
+                _ => Action::Pause,
+            };
+            keyboard.action(key, action, state);
+        }
+    }
+
+    #[test]
+    fn releasing_a_key_falls_back_to_the_one_still_held() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::LastPressed);
+
+        send(&mut keyboard, &[(KeyCode::KeyS, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Down);
+        send(&mut keyboard, &[(KeyCode::KeyW, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(&mut keyboard, &[(KeyCode::KeyS, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(
+            &mut keyboard,
+            &[(KeyCode::KeyS, Pressed), (KeyCode::KeyS, Released)],
+        );
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(&mut keyboard, &[(KeyCode::KeyW, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+    }
+
+    #[test]
+    fn key_repeat_keeps_a_key_in_its_place() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::LastPressed);
+
+        send(
+            &mut keyboard,
+            &[
+                (KeyCode::KeyW, Pressed),
+                (KeyCode::KeyS, Pressed),
+                (KeyCode::KeyW, Pressed),
+                (KeyCode::KeyW, Pressed),
+            ],
+        );
+        assert_eq!(keyboard.current(), crate::pong::Input::Down);
+
+        // A single release ends the key however many repeats came before.
+        send(&mut keyboard, &[(KeyCode::KeyS, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(&mut keyboard, &[(KeyCode::KeyW, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+    }
+
+    #[test]
+    fn keys_for_the_same_direction_are_tracked_apart() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::LastPressed);
+
+        send(
+            &mut keyboard,
+            &[
+                (KeyCode::KeyW, Pressed),
+                (KeyCode::ArrowUp, Pressed),
+                (KeyCode::KeyW, Released),
+            ],
+        );
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(&mut keyboard, &[(KeyCode::ArrowUp, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+    }
+
+    #[test]
+    fn opposing_keys_cancel_out() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::CancelOut);
+
+        send(&mut keyboard, &[(KeyCode::KeyW, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+        send(&mut keyboard, &[(KeyCode::KeyS, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+        send(&mut keyboard, &[(KeyCode::KeyW, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Down);
+    }
+
+    #[test]
+    fn other_actions_are_ignored() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::LastPressed);
+
+        keyboard.action(KeyCode::ArrowUp, Action::P2Up, Pressed);
+        send(&mut keyboard, &[(KeyCode::Space, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+    }
+
+    #[test]
+    fn release_all_forgets_held_keys() {
+        let mut keyboard = keyboard(crate::config::OpposingKeys::LastPressed);
+
+        send(
+            &mut keyboard,
+            &[(KeyCode::KeyW, Pressed), (KeyCode::KeyS, Pressed)],
+        );
+        keyboard.release_all();
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+
+        // Releases that arrive afterwards change nothing.
+        send(&mut keyboard, &[(KeyCode::KeyS, Released)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::None);
+        send(&mut keyboard, &[(KeyCode::KeyW, Pressed)]);
+        assert_eq!(keyboard.current(), crate::pong::Input::Up);
+    }
+}
@@ -1,44 +1,84 @@
 use wgpu::util::DeviceExt;
 
+const NET_WIDTH: f32 = 4.0;
+const NET_DASH: f32 = 20.0;
+const NET_GAP: f32 = 15.0;
+const NET_COLOR: [f32; 3] = [0.35, 0.35, 0.35];
+const WALL_WIDTH: f32 = 6.0;
+const OBSTACLE_COLOR: [f32; 3] = [0.6, 0.6, 0.6];
+const ARENA_COLOR: [f32; 3] = [0.0, 0.0, 0.0];
+const LETTERBOX_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.008,
+    g: 0.008,
+    b: 0.008,
+    a: 1.0,
+};
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub seed: u64,
+    pub tick_rate: f32,
+    pub arena: winit::dpi::PhysicalSize<u32>,
+    pub rules: crate::rules::Ruleset,
+    pub config: crate::config::GameConfig,
+    pub level: crate::level::Level,
+    pub display: crate::config::DisplayConfig,
+    pub input: crate::config::InputConfig,
+    pub bindings: crate::bindings::Bindings,
+    pub players: [crate::controls::PlayerKind; crate::rules::MAX_PLAYERS],
+    pub replay: Option<crate::replay::Replay>,
+    pub net: Option<crate::net::NetOptions>,
+    pub snapshot: Option<crate::snapshot::Snapshot>,
+}
+
 pub struct Engine {
     pub size: winit::dpi::PhysicalSize<u32>,
-    pub surface: wgpu::Surface<'static>,
+    pub surface: Option<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub render_pipeline: wgpu::RenderPipeline,
+    pub transform_buffer: wgpu::Buffer,
+    pub transform_bind_group: wgpu::BindGroup,
     pub controller: crate::pong::Controller,
+    pub controls: Vec<Box<dyn crate::controls::PaddleControl>>,
+    pub players: [crate::controls::PlayerKind; crate::rules::MAX_PLAYERS],
+    pub timestep: crate::timestep::FixedTimestep,
+    pub previous_frame: crate::pong::Frame,
+    pub batch: crate::batch::QuadBatch,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
-    pub vertices: Vec<crate::vertex::Vertex>,
+    pub capacity: usize,
     pub num_indices: u32,
+    pub recorder: Option<crate::replay::Replay>,
+    pub playback: Option<crate::replay::Playback>,
+    pub net: Option<crate::net::Session>,
+    pub quicksave: Option<crate::snapshot::Snapshot>,
+    pub bindings: crate::bindings::Bindings,
+    pub gamepads: Box<dyn crate::gamepad::Backend>,
+    pub paused: bool,
 }
 
 impl Engine {
-    pub fn screen_space_to_clip_space(
-        pos: &[f32; 3],
-        size: &winit::dpi::PhysicalSize<u32>,
-    ) -> [f32; 3] {
-        [
-            (2.0 * (pos[0] / size.width as f32)) - 1.0,
-            (2.0 * (pos[1] / size.height as f32)) - 1.0,
-            1.0,
-        ]
-    }
+    fn create_quad_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Buffer"),
+            size: (capacity * 4 * std::mem::size_of::<crate::vertex::Vertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&crate::batch::QuadBatch::indices(capacity)),
+            usage: wgpu::BufferUsages::INDEX,
+        });
 
-    #[allow(unused)]
-    pub fn clip_space_to_screen_space(
-        pos: &[f32; 3],
-        size: &winit::dpi::PhysicalSize<u32>,
-    ) -> [f32; 3] {
-        [
-            (pos[0] + 1.0) / 2.0 * size.width as f32,
-            (pos[1] + 1.0) / 2.0 * size.height as f32,
-            1.0,
-        ]
+        (vertex_buffer, index_buffer)
     }
 
-    pub async fn new(window: &winit::window::Window) -> Self {
+    pub async fn new(window: &winit::window::Window, options: &Options) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::default();
@@ -70,13 +110,21 @@ impl Engine {
             .unwrap();
 
         let caps = surface.get_capabilities(&adapter);
+        // The palette is authored in linear space, so prefer a surface that
+        // encodes to sRGB on write.
+        let format = caps
+            .formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .unwrap_or(caps.formats[0]);
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: caps.formats[0],
+            format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: Engine::present_mode(options.display.vsync, &caps.present_modes),
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -84,76 +132,174 @@ impl Engine {
 
         surface.configure(&device, &config);
 
+        Engine::from_device(size, Some(surface), device, queue, config, options)
+    }
+
+    // Falls back to Fifo, which every surface supports, when the requested
+    // mode is unavailable. Mailbox degrades to Immediate first since both
+    // avoid waiting on vblank.
+    fn present_mode(
+        requested: crate::config::PresentMode,
+        supported: &[wgpu::PresentMode],
+    ) -> wgpu::PresentMode {
+        let preferred: &[wgpu::PresentMode] = match requested {
+            crate::config::PresentMode::Fifo => &[wgpu::PresentMode::Fifo],
+            crate::config::PresentMode::Mailbox => {
+                &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+            }
+            crate::config::PresentMode::Immediate => &[wgpu::PresentMode::Immediate],
+        };
+
+        let mode = preferred
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(wgpu::PresentMode::Fifo);
+        if mode != preferred[0] {
+            eprintln!(
+                "{:?} is not supported, presenting with {:?}",
+                preferred[0], mode
+            );
+        }
+        mode
+    }
+
+    // Builds an engine that renders into offscreen textures only, preferring
+    // the software fallback adapter so it works on machines without a GPU.
+    pub async fn new_headless(
+        size: winit::dpi::PhysicalSize<u32>,
+        options: &Options,
+    ) -> Result<Self, String> {
+        let instance = wgpu::Instance::default();
+
+        let mut adapter = None;
+        for force_fallback_adapter in [true, false] {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or("no graphics adapter available")?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Device Descriptor"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        // Only matches played in a window are recorded.
+        let mut engine = Engine::from_device(size, None, device, queue, config, options);
+        engine.recorder = None;
+        Ok(engine)
+    }
+
+    fn from_device(
+        size: winit::dpi::PhysicalSize<u32>,
+        surface: Option<wgpu::Surface<'static>>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        options: &Options,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/default.wgsl").into()),
         });
 
+        let transform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Transform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Pipeline Layout Descriptor"),
-                bind_group_layouts: &[],
+                bind_group_layouts: &[&transform_layout],
                 push_constant_ranges: &[],
             });
 
-        let controller = crate::pong::Controller::new(&size);
-
-        let vertices = {
-            let mut vertices: Vec<crate::vertex::Vertex> = Vec::new();
-
-            for i in 0..12 {
-                let index = {
-                    match i {
-                        0..=3 => 0,
-                        4..=7 => 1,
-                        _ => 2,
-                    }
-                };
-
-                if index == 0 || index == 1 {
-                    vertices.push(crate::vertex::Vertex {
-                        position: controller.players[index].vertices[i % 4],
-                        color: [1.0, 1.0, 1.0],
-                    });
-                } else {
-                    vertices.push(crate::vertex::Vertex {
-                        position: controller.ball.vertices[i % 4],
-                        color: [1.0, 1.0, 1.0],
-                    });
-                }
-            }
-            vertices
+        let (mut controller, tick_rate) = match &options.replay {
+            Some(replay) => (replay.controller(), replay.header.tick_rate),
+            None => (
+                crate::pong::Controller::new(
+                    &options.arena,
+                    options.seed,
+                    options.rules.clone(),
+                    options.config.clone(),
+                    options.level.clone(),
+                ),
+                options.tick_rate,
+            ),
         };
-
-        let indices: Vec<u16> = {
-            let mut indices: Vec<u16> = Vec::new();
-
-            for i in 0..3 {
-                indices.push(i * 4);
-                indices.push((i * 4) + 1);
-                indices.push((i * 4) + 2);
-                indices.push(i * 4);
-                indices.push((i * 4) + 2);
-                indices.push((i * 4) + 3);
+        let controls = (0..controller.players.len())
+            .map(|player| options.players[player].build(player, options.seed, &options.input))
+            .collect();
+        let timestep = crate::timestep::FixedTimestep::new(tick_rate);
+        let playback = options.replay.clone().map(crate::replay::Playback::new);
+        let recorder = playback
+            .is_none()
+            .then(|| crate::replay::Replay::record(&controller, tick_rate));
+        // Replays always start from a fresh match, so a restored position is
+        // not recorded.
+        let recorder = match &options.snapshot {
+            Some(snapshot) if playback.is_none() => {
+                controller.restore(snapshot);
+                None
             }
-
-            indices.append(&mut vec![0; indices.len() * 2 % 4]); // padding
-
-            indices
+            _ => recorder,
         };
+        let previous_frame = controller.frame();
 
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        let transform = crate::viewport::Viewport::new(controller.size(), size).transform();
+        let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer"),
+            contents: bytemuck::bytes_of(&transform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
+        let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Transform Bind Group"),
+            layout: &transform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: transform_buffer.as_entire_binding(),
+            }],
         });
 
+        let capacity = 64;
+        let (vertex_buffer, index_buffer) = Engine::create_quad_buffers(&device, capacity);
+
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
@@ -201,8 +347,6 @@ impl Engine {
             multiview: None,
         });
 
-        let num_indices = indices.len() as u32;
-
         Self {
             size,
             surface,
@@ -210,48 +354,366 @@ impl Engine {
             queue,
             config,
             render_pipeline,
+            transform_buffer,
+            transform_bind_group,
             controller,
+            controls,
+            players: options.players,
+            timestep,
+            previous_frame,
+            batch: crate::batch::QuadBatch::default(),
             vertex_buffer,
             index_buffer,
-            vertices,
-            num_indices,
+            capacity,
+            num_indices: 0,
+            recorder,
+            playback,
+            net: None,
+            quicksave: None,
+            bindings: options.bindings.clone(),
+            gamepads: Box::new(crate::gamepad::NoGamepads),
+            paused: false,
         }
     }
 
     pub fn update(&mut self, _dt: &std::time::Duration) {
-        let dt = _dt.as_secs_f32();
+        while let Some(event) = self.gamepads.poll() {
+            self.gamepad(event);
+        }
+
+        let mut dt = _dt.as_secs_f32();
+        let mut steps = 0;
+
+        if let Some(playback) = &mut self.playback {
+            dt = if playback.paused {
+                0.0
+            } else {
+                dt * playback.speed
+            };
+            steps = playback.take_step() as u32;
+        } else if self.paused {
+            dt = 0.0;
+        }
+
+        for _ in 0..self.timestep.advance(dt) + steps {
+            self.tick();
+        }
 
+        self.build_frame();
+    }
+
+    // Hands both paddles over to a network session: the local one stays on
+    // this machine's controls and the other is driven by the peer.
+    pub fn connect(
+        &mut self,
+        transport: Box<dyn crate::net::Transport>,
+        options: &crate::net::NetOptions,
+    ) {
+        self.recorder = None;
+        self.net = Some(crate::net::Session::new(
+            transport,
+            &self.controller,
+            options.player,
+            options.delay,
+            self.timestep.rate(),
+        ));
+    }
+
+    pub fn tick(&mut self) {
+        let dt = self.timestep.tick();
+        self.previous_frame = self.controller.frame();
+
+        if let Some(session) = &mut self.net {
+            let local = session.local();
+            let input = self.controls[local].input(local, &self.controller, dt);
+            if let crate::rules::MatchState::Serving { server } = self.controller.state() {
+                if server == local && self.controls[local].serve(local, &self.controller, dt) {
+                    session.serve();
+                }
+            }
+
+            if let Err(e) = session.advance(&mut self.controller, input) {
+                eprintln!("network error: {}", e);
+            }
+            return;
+        }
+
+        if let Some(playback) = &mut self.playback {
+            let Some(tick) = playback.next_tick() else {
+                playback.paused = true;
+                return;
+            };
+
+            for (player, input) in self.controller.players.iter_mut().zip(tick.inputs) {
+                player.input = input;
+            }
+            if tick.serve {
+                self.controller.serve();
+            }
+        } else {
+            for (player, control) in self.controls.iter_mut().enumerate() {
+                self.controller.players[player].input = control.input(player, &self.controller, dt);
+            }
+
+            if let crate::rules::MatchState::Serving { server } = self.controller.state() {
+                if self.controls[server].serve(server, &self.controller, dt) {
+                    self.controller.serve();
+                }
+            }
+        }
+
+        let mut inputs = [crate::pong::Input::None; crate::rules::MAX_PLAYERS];
+        for (input, player) in inputs.iter_mut().zip(&self.controller.players) {
+            *input = player.input;
+        }
+        let tick = crate::replay::TickInput {
+            inputs,
+            serve: self.controller.serve_requested(),
+        };
         self.controller.update(dt);
 
-        // Update players positions
-        for i in 0..=7 {
-            let player = {
-                if i & 1 << 2 != 0 {
-                    1
-                } else {
-                    0
+        if let Some(recorder) = &mut self.recorder {
+            if recorder.ticks.len() as u64 >= crate::replay::MAX_TICKS {
+                self.recorder = None;
+                println!("replay recording stopped at its length limit");
+                return;
+            }
+            recorder.ticks.push(tick);
+
+            if let crate::rules::MatchState::GameOver { .. } = self.controller.state() {
+                self.save_replay();
+            }
+        }
+    }
+
+    fn save_replay(&mut self) {
+        let Some(replay) = self.recorder.take() else {
+            return;
+        };
+
+        let path = crate::replay::Replay::default_path();
+        match replay.save(&path) {
+            Ok(()) => println!("saved replay to {}", path.display()),
+            Err(e) => eprintln!("failed to save replay: {}", e),
+        }
+    }
+
+    fn restart_match(&mut self) {
+        self.controller.reset_match();
+        self.previous_frame = self.controller.frame();
+        self.recorder = Some(crate::replay::Replay::record(
+            &self.controller,
+            self.timestep.rate(),
+        ));
+    }
+
+    fn quicksave(&mut self) {
+        let snapshot = self.controller.snapshot();
+        let path = crate::snapshot::Snapshot::default_path();
+        match snapshot.save(&path) {
+            Ok(()) => println!("saved snapshot to {}", path.display()),
+            Err(e) => eprintln!("failed to save snapshot: {}", e),
+        }
+        self.quicksave = Some(snapshot);
+    }
+
+    fn quickload(&mut self) {
+        let Some(snapshot) = &self.quicksave else {
+            return;
+        };
+
+        self.controller.restore(snapshot);
+        self.previous_frame = self.controller.frame();
+        if self.recorder.take().is_some() {
+            println!("replay recording stopped after loading a snapshot");
+        }
+    }
+
+    pub fn build_frame(&mut self) {
+        let frame = self
+            .previous_frame
+            .interpolate(&self.controller.frame(), self.timestep.alpha());
+
+        self.batch.clear();
+        let size = self.controller.size();
+        self.draw_quad(
+            crate::collision::Aabb::new(0.0, 0.0, size.width as f32, size.height as f32),
+            ARENA_COLOR,
+        );
+        self.draw_net();
+        self.draw_walls();
+        let obstacles: Vec<_> = self
+            .controller
+            .level()
+            .obstacles
+            .iter()
+            .zip(frame.obstacles)
+            .map(|(obstacle, position)| {
+                crate::collision::Aabb::new(position.x, position.y, obstacle.width, obstacle.height)
+            })
+            .collect();
+        for rect in obstacles {
+            self.draw_quad(rect, OBSTACLE_COLOR);
+        }
+        for (player, position) in frame.players.into_iter().enumerate() {
+            let paddle = &self.controller.players[player];
+            if paddle.eliminated() {
+                continue;
+            }
+            let rect =
+                crate::collision::Aabb::new(position.x, position.y, paddle.width, paddle.height);
+            self.draw_quad(rect, [1.0, 1.0, 1.0]);
+        }
+        let size = self.controller.config().ball_size;
+        for position in frame.balls {
+            self.draw_quad(
+                crate::collision::Aabb::new(position.x, position.y, size, size),
+                [1.0, 1.0, 1.0],
+            );
+        }
+        self.draw_hud();
+
+        self.flush();
+    }
+
+    pub fn draw_quad(&mut self, rect: crate::collision::Aabb, color: [f32; 3]) {
+        self.batch.push(rect, color);
+    }
+
+    fn draw_net(&mut self) {
+        let size = self.controller.size();
+        let x = (size.width as f32 - NET_WIDTH) / 2.0;
+
+        let mut y = NET_GAP / 2.0;
+        while y < size.height as f32 {
+            self.draw_quad(
+                crate::collision::Aabb::new(x, y, NET_WIDTH, NET_DASH),
+                NET_COLOR,
+            );
+            y += NET_DASH + NET_GAP;
+        }
+    }
+
+    // Whole sides are only marked when playing for lives, where they close
+    // as players are knocked out. Stretches around a level's goals always are.
+    fn draw_walls(&mut self) {
+        let size = self.controller.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        for side in crate::pong::Side::ALL {
+            let stretches = if self.controller.walled(side) {
+                if !self.controller.rules().uses_lives() {
+                    continue;
                 }
+                let length = side
+                    .axis()
+                    .split(crate::pong::Vector2D::new(width, height))
+                    .1;
+                vec![(0.0, length)]
+            } else {
+                self.controller.level().blocked(side)
             };
 
-            self.vertices[i].position[1] = self.controller.players[player].vertices[i % 4][1];
+            for (start, end) in stretches {
+                let rect = match side {
+                    crate::pong::Side::Left => {
+                        crate::collision::Aabb::new(0.0, start, WALL_WIDTH, end - start)
+                    }
+                    crate::pong::Side::Right => crate::collision::Aabb::new(
+                        width - WALL_WIDTH,
+                        start,
+                        WALL_WIDTH,
+                        end - start,
+                    ),
+                    crate::pong::Side::Bottom => {
+                        crate::collision::Aabb::new(start, 0.0, end - start, WALL_WIDTH)
+                    }
+                    crate::pong::Side::Top => crate::collision::Aabb::new(
+                        start,
+                        height - WALL_WIDTH,
+                        end - start,
+                        WALL_WIDTH,
+                    ),
+                };
+                self.draw_quad(rect, NET_COLOR);
+            }
         }
+    }
+
+    fn draw_hud(&mut self) {
+        // The server is prompted the way they serve. Anyone can start a new
+        // match, with the serve key when nobody else could.
+        let serve_prompt = match self.controller.state() {
+            _ if self.playback.is_some() => None,
+            crate::rules::MatchState::Serving { server }
+                if self
+                    .net
+                    .as_ref()
+                    .is_none_or(|session| session.local() == server) =>
+            {
+                self.players[server].serve_prompt(&self.bindings)
+            }
+            crate::rules::MatchState::GameOver { .. } => self.players[..self.controls.len()]
+                .iter()
+                .find_map(|kind| kind.serve_prompt(&self.bindings))
+                .or_else(|| crate::controls::PlayerKind::Keyboard.serve_prompt(&self.bindings)),
+            _ => None,
+        };
+
+        let mut hud = crate::hud::Hud::build(&self.controller, serve_prompt.as_deref());
+        if let Some(playback) = &self.playback {
+            let status = if playback.finished() {
+                "REPLAY FINISHED".to_string()
+            } else if playback.paused {
+                "REPLAY PAUSED".to_string()
+            } else {
+                format!("REPLAY {}X", playback.speed)
+            };
+            hud.status(&status, &self.controller);
+        }
+        if self.paused {
+            hud.status("PAUSED", &self.controller);
+        }
+        if let Some(session) = &self.net {
+            if session.stalled() {
+                let status = format!("WAITING FOR PLAYER {}", 2 - session.local());
+                hud.status(&status, &self.controller);
+            }
+        }
+        self.batch.extend(hud.scores, crate::hud::SCORE_COLOR);
+        self.batch.extend(hud.messages, crate::hud::MESSAGE_COLOR);
+    }
 
-        // Update ball position
-        for i in 8..=11 {
-            self.vertices[i].position = self.controller.ball.vertices[i % 4];
+    // Uploads the quads submitted this frame, growing the GPU buffers when
+    // the batch no longer fits.
+    fn flush(&mut self) {
+        if self.batch.len() > self.capacity {
+            self.capacity = self.batch.len().next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) =
+                Engine::create_quad_buffers(&self.device, self.capacity);
         }
 
+        let vertices = self.batch.vertices();
         self.queue
-            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.num_indices = (self.batch.len() * 6) as u32;
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
 
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&view);
+        output.present();
 
+        Ok(())
+    }
+
+    fn draw(&self, view: &wgpu::TextureView) {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -261,15 +723,10 @@ impl Engine {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass Descriptor"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.0,
-                        g: 0.0,
-                        b: 0.0,
-                        a: 1.0,
-                    }),
+                    load: wgpu::LoadOp::Clear(LETTERBOX_COLOR),
                     store: wgpu::StoreOp::Store,
                 },
             })],
@@ -278,16 +735,129 @@ impl Engine {
             occlusion_query_set: None,
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        if self.num_indices > 0 {
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.transform_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
         drop(render_pass);
 
         self.queue.submit(Some(encoder.finish()));
-        output.present();
+    }
 
-        Ok(())
+    // Renders the current frame into an offscreen texture and reads it back
+    // as tightly packed RGBA rows, top row first.
+    pub fn capture(
+        &mut self,
+    ) -> Result<crate::screenshot::Screenshot, crate::screenshot::ScreenshotError> {
+        let width = self.config.width;
+        let height = self.config.height;
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        self.draw(&texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("map_async callback dropped")
+            .map_err(crate::screenshot::ScreenshotError::Map)?;
+
+        let bgra = matches!(
+            self.config.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let mut rgba = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+        {
+            for pixel in row[..unpadded_bytes_per_row as usize].chunks(4) {
+                if bgra {
+                    rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                } else {
+                    rgba.extend_from_slice(pixel);
+                }
+            }
+        }
+        buffer.unmap();
+
+        Ok(crate::screenshot::Screenshot {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    pub fn save_screenshot(
+        &mut self,
+        path: &std::path::Path,
+    ) -> Result<(), crate::screenshot::ScreenshotError> {
+        self.capture()?.save_png(path)
+    }
+
+    pub fn viewport(&self) -> crate::viewport::Viewport {
+        crate::viewport::Viewport::new(self.controller.size(), self.size)
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -295,7 +865,13 @@ impl Engine {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+
+            let transform = self.viewport().transform();
+            self.queue
+                .write_buffer(&self.transform_buffer, 0, bytemuck::bytes_of(&transform));
         }
     }
 
@@ -310,40 +886,93 @@ impl Engine {
             ..
         } = event
         {
-            match (key, state) {
-                (winit::keyboard::KeyCode::KeyW, _) => match state {
-                    winit::event::ElementState::Pressed => {
-                        self.controller.players[0].input = crate::pong::Input::Up;
-                    }
-                    winit::event::ElementState::Released => {
-                        self.controller.players[0].input = crate::pong::Input::None;
+            for action in self.bindings.actions(*key) {
+                if self.playback.is_none() {
+                    for control in &mut self.controls {
+                        control.action(*key, action, *state);
                     }
-                },
-                (winit::keyboard::KeyCode::KeyS, _) => match state {
-                    winit::event::ElementState::Pressed => {
-                        self.controller.players[0].input = crate::pong::Input::Down;
-                    }
-                    winit::event::ElementState::Released => {
-                        self.controller.players[0].input = crate::pong::Input::None;
-                    }
-                },
-                (winit::keyboard::KeyCode::ArrowUp, _) => match state {
-                    winit::event::ElementState::Pressed => {
-                        self.controller.players[1].input = crate::pong::Input::Up;
-                    }
-                    winit::event::ElementState::Released => {
-                        self.controller.players[1].input = crate::pong::Input::None;
-                    }
-                },
-                (winit::keyboard::KeyCode::ArrowDown, _) => match state {
-                    winit::event::ElementState::Pressed => {
-                        self.controller.players[1].input = crate::pong::Input::Down;
-                    }
-                    winit::event::ElementState::Released => {
-                        self.controller.players[1].input = crate::pong::Input::None;
+                }
+                self.action(action, *state);
+            }
+        }
+
+        match event {
+            winit::event::WindowEvent::Focused(false) => {
+                for control in &mut self.controls {
+                    control.release_all();
+                }
+            }
+            winit::event::WindowEvent::CursorMoved { position, .. } => {
+                let position = self.viewport().to_arena(*position);
+                if self.playback.is_none() {
+                    for control in &mut self.controls {
+                        control.cursor(position);
                     }
-                },
-                _ => (),
+                }
+            }
+            // Mouse players serve with a click.
+            winit::event::WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Left,
+                ..
+            } if self.players[..self.controls.len()]
+                .contains(&crate::controls::PlayerKind::Mouse) =>
+            {
+                self.action(crate::bindings::Action::Serve, *state);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn gamepad(&mut self, event: crate::gamepad::Event) {
+        if self.playback.is_none() {
+            for control in &mut self.controls {
+                control.gamepad(&event);
+            }
+        }
+
+        if let crate::gamepad::Event::Button { button, state, .. } = event {
+            if let Some(action) = button.action() {
+                self.action(action, state);
+            }
+        }
+    }
+
+    pub fn action(&mut self, action: crate::bindings::Action, state: winit::event::ElementState) {
+        use crate::bindings::Action;
+
+        let pressed = state == winit::event::ElementState::Pressed;
+        if let Some(playback) = &mut self.playback {
+            if pressed {
+                match action {
+                    Action::Serve | Action::Pause => playback.toggle_pause(),
+                    Action::ReplayStep => playback.step(),
+                    Action::ReplayFaster => playback.faster(),
+                    Action::ReplaySlower => playback.slower(),
+                    _ => (),
+                }
+            }
+        } else {
+            if pressed {
+                match (action, &mut self.net) {
+                    (Action::Serve, Some(session)) => session.serve(),
+                    (Action::Serve, None) if !self.paused => match self.controller.state() {
+                        crate::rules::MatchState::GameOver { .. } => self.restart_match(),
+                        _ => self.controller.serve(),
+                    },
+                    (Action::Pause, None) => self.paused = !self.paused,
+                    (Action::QuickSave, None) => self.quicksave(),
+                    (Action::QuickLoad, None) => self.quickload(),
+                    _ => (),
+                }
+            }
+        }
+
+        if pressed && action == Action::Screenshot {
+            let path = crate::screenshot::Screenshot::default_path();
+            match self.save_screenshot(&path) {
+                Ok(()) => println!("saved screenshot to {}", path.display()),
+                Err(e) => eprintln!("{}", e),
             }
         }
     }
@@ -0,0 +1,58 @@
+pub use crate::pong::Side;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    // Speed is the ball's speed leaving the paddle.
+    PaddleHit {
+        player: usize,
+        offset: f32,
+        speed: f32,
+    },
+    WallBounce {
+        side: Side,
+    },
+    PointScored {
+        player: usize,
+        score: u32,
+    },
+    // Lives are what the player has left, none meaning they are out.
+    LifeLost {
+        player: usize,
+        lives: u32,
+    },
+    Serve {
+        direction: crate::pong::Vector2D,
+    },
+    // Balls counts every ball in play, the new one included.
+    BallSpawned {
+        direction: crate::pong::Vector2D,
+        balls: usize,
+    },
+    BallCollision,
+    ObstacleBounce {
+        obstacle: usize,
+    },
+}
+
+pub type Listener = Box<dyn FnMut(&Event)>;
+
+#[derive(Default)]
+pub struct Listeners(Vec<Listener>);
+
+impl Listeners {
+    pub fn push(&mut self, listener: impl FnMut(&Event) + 'static) {
+        self.0.push(Box::new(listener));
+    }
+
+    pub fn notify(&mut self, event: &Event) {
+        for listener in &mut self.0 {
+            listener(event);
+        }
+    }
+}
+
+impl std::fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Listeners({})", self.0.len())
+    }
+}
@@ -0,0 +1,27 @@
+use std::io::Write;
+
+// Creates `path` and any missing parent directories, then hands `write` a
+// buffered writer that is flushed once it returns.
+pub fn save<E: From<std::io::Error>>(
+    path: &std::path::Path,
+    write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), E>,
+) -> Result<(), E> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write(&mut writer)?;
+    Ok(writer.flush()?)
+}
+
+// A path in `dir` named after the current time in milliseconds, so files
+// saved during play sort by when they were taken.
+pub fn timestamped_path(dir: &str, extension: &str) -> std::path::PathBuf {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+
+    std::path::PathBuf::from(dir).join(format!("pong-{}.{}", millis, extension))
+}
@@ -0,0 +1,299 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    South,
+    Start,
+    Other,
+}
+
+impl Button {
+    pub fn action(self) -> Option<crate::bindings::Action> {
+        match self {
+            Button::South => Some(crate::bindings::Action::Serve),
+            Button::Start => Some(crate::bindings::Action::Pause),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    LeftStickX,
+    LeftStickY,
+    Other,
+}
+
+// Gamepads are told apart by the id their backend gives them, which is also
+// how they are assigned to players.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Connected(usize),
+    Disconnected(usize),
+    Button {
+        id: usize,
+        button: Button,
+        state: winit::event::ElementState,
+    },
+    // Stick values range from -1 to 1, positive being up or right.
+    Axis {
+        id: usize,
+        axis: Axis,
+        value: f32,
+    },
+}
+
+pub trait Backend {
+    fn poll(&mut self) -> Option<Event>;
+}
+
+// Used when gamepad support is compiled out or unavailable.
+#[derive(Debug, Default)]
+pub struct NoGamepads;
+
+impl Backend for NoGamepads {
+    fn poll(&mut self) -> Option<Event> {
+        None
+    }
+}
+
+// Replays queued events, standing in for real devices.
+#[derive(Debug, Default)]
+pub struct MockGamepads {
+    events: std::collections::VecDeque<Event>,
+}
+
+impl MockGamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+}
+
+impl Backend for MockGamepads {
+    fn poll(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub struct Gilrs {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gilrs {
+    fn button(button: gilrs::Button) -> Button {
+        match button {
+            gilrs::Button::DPadUp => Button::DPadUp,
+            gilrs::Button::DPadDown => Button::DPadDown,
+            gilrs::Button::DPadLeft => Button::DPadLeft,
+            gilrs::Button::DPadRight => Button::DPadRight,
+            gilrs::Button::South => Button::South,
+            gilrs::Button::Start => Button::Start,
+            _ => Button::Other,
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl Backend for Gilrs {
+    fn poll(&mut self) -> Option<Event> {
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let id = usize::from(id);
+            let event = match event {
+                gilrs::EventType::Connected => Event::Connected(id),
+                gilrs::EventType::Disconnected => Event::Disconnected(id),
+                gilrs::EventType::ButtonPressed(button, _) => Event::Button {
+                    id,
+                    button: Gilrs::button(button),
+                    state: winit::event::ElementState::Pressed,
+                },
+                gilrs::EventType::ButtonReleased(button, _) => Event::Button {
+                    id,
+                    button: Gilrs::button(button),
+                    state: winit::event::ElementState::Released,
+                },
+                gilrs::EventType::AxisChanged(axis, value, _) => Event::Axis {
+                    id,
+                    axis: match axis {
+                        gilrs::Axis::LeftStickX => Axis::LeftStickX,
+                        gilrs::Axis::LeftStickY => Axis::LeftStickY,
+                        _ => Axis::Other,
+                    },
+                    value,
+                },
+                _ => continue,
+            };
+            return Some(event);
+        }
+        None
+    }
+}
+
+pub fn backend() -> Box<dyn Backend> {
+    #[cfg(feature = "gamepad")]
+    match gilrs::Gilrs::new() {
+        Ok(gilrs) => return Box::new(Gilrs { gilrs }),
+        Err(e) => eprintln!("gamepads unavailable: {}", e),
+    }
+
+    Box::new(NoGamepads)
+}
+
+// Rescales a stick value so the deadzone reads as zero and movement starts
+// smoothly at its edge.
+pub fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let magnitude = value.abs().min(1.0);
+    if magnitude <= deadzone {
+        0.0
+    } else {
+        value.signum() * (magnitude - deadzone) / (1.0 - deadzone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::PaddleControl;
+    use crate::pong::{Input, Side};
+
+    const DEADZONE: f32 = 0.25;
+
+    fn drain(gamepads: &mut MockGamepads, control: &mut crate::controls::Gamepad) {
+        while let Some(event) = gamepads.poll() {
+            control.gamepad(&event);
+        }
+    }
+
+    fn stick(id: usize, axis: Axis, value: f32) -> Event {
+        Event::Axis { id, axis, value }
+    }
+
+    fn button(id: usize, button: Button, state: winit::event::ElementState) -> Event {
+        Event::Button { id, button, state }
+    }
+
+    #[test]
+    fn deadzone_is_rescaled() {
+        assert_eq!(apply_deadzone(0.1, DEADZONE), 0.0);
+        assert_eq!(apply_deadzone(-0.25, DEADZONE), 0.0);
+        assert_eq!(apply_deadzone(0.625, DEADZONE), 0.5);
+        assert_eq!(apply_deadzone(-1.0, DEADZONE), -1.0);
+        assert_eq!(apply_deadzone(1.5, DEADZONE), 1.0);
+    }
+
+    #[test]
+    fn stick_moves_the_paddle_past_the_deadzone() {
+        let mut gamepads = MockGamepads::new();
+        let mut control = crate::controls::Gamepad::new(0, DEADZONE);
+
+        gamepads.push(stick(0, Axis::LeftStickY, 0.2));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::None);
+
+        gamepads.push(stick(0, Axis::LeftStickY, 0.625));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::Axis(64));
+
+        gamepads.push(stick(0, Axis::LeftStickY, -1.0));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Right), Input::Axis(-Input::AXIS_MAX));
+
+        // Paddles on the bottom and top follow the horizontal stick.
+        assert_eq!(control.current(Side::Bottom), Input::None);
+        gamepads.push(stick(0, Axis::LeftStickX, 0.625));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Top), Input::Axis(64));
+    }
+
+    #[test]
+    fn dpad_overrides_the_stick() {
+        let mut gamepads = MockGamepads::new();
+        let mut control = crate::controls::Gamepad::new(0, DEADZONE);
+
+        gamepads.push(stick(0, Axis::LeftStickY, -1.0));
+        gamepads.push(button(
+            0,
+            Button::DPadUp,
+            winit::event::ElementState::Pressed,
+        ));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::Up);
+
+        // Both directions held fall back to the stick.
+        gamepads.push(button(
+            0,
+            Button::DPadDown,
+            winit::event::ElementState::Pressed,
+        ));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::Axis(-Input::AXIS_MAX));
+
+        gamepads.push(button(
+            0,
+            Button::DPadUp,
+            winit::event::ElementState::Released,
+        ));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::Down);
+
+        gamepads.push(button(
+            0,
+            Button::DPadRight,
+            winit::event::ElementState::Pressed,
+        ));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Bottom), Input::Up);
+    }
+
+    #[test]
+    fn other_gamepads_are_ignored() {
+        let mut gamepads = MockGamepads::new();
+        let mut control = crate::controls::Gamepad::new(1, DEADZONE);
+
+        gamepads.push(stick(0, Axis::LeftStickY, 1.0));
+        gamepads.push(button(
+            0,
+            Button::DPadDown,
+            winit::event::ElementState::Pressed,
+        ));
+        gamepads.push(Event::Disconnected(0));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Right), Input::None);
+
+        gamepads.push(stick(1, Axis::LeftStickY, 1.0));
+        gamepads.push(Event::Disconnected(0));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Right), Input::Axis(Input::AXIS_MAX));
+    }
+
+    #[test]
+    fn disconnecting_resets_the_gamepad() {
+        let mut gamepads = MockGamepads::new();
+        let mut control = crate::controls::Gamepad::new(0, DEADZONE);
+
+        gamepads.push(Event::Connected(0));
+        gamepads.push(stick(0, Axis::LeftStickY, 1.0));
+        gamepads.push(button(
+            0,
+            Button::DPadLeft,
+            winit::event::ElementState::Pressed,
+        ));
+        gamepads.push(Event::Disconnected(0));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::None);
+        assert_eq!(control.current(Side::Bottom), Input::None);
+
+        // Reconnecting starts from rest rather than the last known state.
+        gamepads.push(Event::Connected(0));
+        gamepads.push(stick(0, Axis::LeftStickY, -0.625));
+        drain(&mut gamepads, &mut control);
+        assert_eq!(control.current(Side::Left), Input::Axis(-64));
+    }
+}
@@ -0,0 +1,133 @@
+pub struct Simulation {
+    pub controller: crate::pong::Controller,
+    pub dt: f32,
+    pub ticks: u64,
+    pub auto_serve: bool,
+    pub matches_won: [u32; crate::rules::MAX_PLAYERS],
+    pub stats: Stats,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub serves: u64,
+    pub paddle_hits: u64,
+    pub wall_bounces: u64,
+    pub obstacle_bounces: u64,
+    pub points: u64,
+    pub lives_lost: u64,
+    pub extra_balls: u64,
+    pub ball_collisions: u64,
+}
+
+impl Stats {
+    pub fn record(&mut self, event: &crate::event::Event) {
+        match event {
+            crate::event::Event::Serve { .. } => self.serves += 1,
+            crate::event::Event::PaddleHit { .. } => self.paddle_hits += 1,
+            crate::event::Event::WallBounce { .. } => self.wall_bounces += 1,
+            crate::event::Event::PointScored { .. } => self.points += 1,
+            crate::event::Event::LifeLost { .. } => self.lives_lost += 1,
+            crate::event::Event::BallSpawned { .. } => self.extra_balls += 1,
+            crate::event::Event::BallCollision => self.ball_collisions += 1,
+            crate::event::Event::ObstacleBounce { .. } => self.obstacle_bounces += 1,
+        }
+    }
+}
+
+impl Simulation {
+    pub fn new(
+        size: winit::dpi::PhysicalSize<u32>,
+        dt: f32,
+        seed: u64,
+        rules: crate::rules::Ruleset,
+        config: crate::config::GameConfig,
+        level: crate::level::Level,
+    ) -> Self {
+        Self {
+            controller: crate::pong::Controller::new(&size, seed, rules, config, level),
+            dt,
+            ticks: 0,
+            auto_serve: true,
+            matches_won: [0; crate::rules::MAX_PLAYERS],
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn from_replay(replay: &crate::replay::Replay) -> Self {
+        let header = &replay.header;
+        let mut simulation = Simulation::new(
+            header.size,
+            1.0 / header.tick_rate,
+            header.seed,
+            header.rules.clone(),
+            header.config.clone(),
+            header.level.clone(),
+        );
+        simulation.auto_serve = false;
+        simulation
+    }
+
+    pub fn step_replay(&mut self, tick: crate::replay::TickInput) {
+        if tick.serve {
+            self.controller.serve();
+        }
+        self.step_with(tick.inputs);
+    }
+
+    pub fn set_input(&mut self, player: usize, input: crate::pong::Input) {
+        self.controller.players[player].input = input;
+    }
+
+    pub fn step(&mut self) {
+        if self.auto_serve {
+            self.serve_or_restart();
+        }
+
+        self.controller.update(self.dt);
+        self.ticks += 1;
+
+        for event in self.controller.events() {
+            self.stats.record(event);
+        }
+    }
+
+    fn serve_or_restart(&mut self) {
+        match self.controller.state() {
+            crate::rules::MatchState::Serving { .. } => self.controller.serve(),
+            crate::rules::MatchState::GameOver { winner } => {
+                self.matches_won[winner] += 1;
+                self.controller.reset_match();
+            }
+            _ => (),
+        }
+    }
+
+    // Inputs past the number of players are ignored.
+    pub fn step_with(&mut self, inputs: [crate::pong::Input; crate::rules::MAX_PLAYERS]) {
+        let players = self.controller.players.len();
+        for (player, input) in inputs.into_iter().enumerate().take(players) {
+            self.set_input(player, input);
+        }
+        self.step();
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    pub fn run_controlled(
+        &mut self,
+        controls: &mut [Box<dyn crate::controls::PaddleControl>],
+        ticks: u64,
+    ) {
+        for _ in 0..ticks {
+            for (player, control) in controls.iter_mut().enumerate() {
+                let input = control.input(player, &self.controller, self.dt);
+                self.set_input(player, input);
+            }
+            self.step();
+        }
+    }
+}
@@ -0,0 +1,135 @@
+const SCORE_SCALE: f32 = 8.0;
+const MESSAGE_SCALE: f32 = 3.0;
+const MARGIN: f32 = 20.0;
+
+pub const SCORE_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+pub const MESSAGE_COLOR: [f32; 3] = [0.7, 0.7, 0.7];
+
+#[derive(Debug, Default)]
+pub struct Hud {
+    pub scores: Vec<crate::collision::Aabb>,
+    pub messages: Vec<crate::collision::Aabb>,
+}
+
+impl Hud {
+    // The serve prompt, like "PRESS SPACE", is left out when nobody
+    // watching can serve.
+    pub fn build(controller: &crate::pong::Controller, serve_prompt: Option<&str>) -> Self {
+        let size = controller.size();
+        let width = size.width as f32;
+        let height = size.height as f32;
+        let players = &controller.players;
+
+        let mut hud = Hud::default();
+
+        let score_y = height - MARGIN - crate::text::height(SCORE_SCALE);
+        let line = crate::text::height(MESSAGE_SCALE) + MARGIN;
+        let mut info_y = score_y - line;
+        if controller.rules().uses_lives() {
+            hud.lives(controller, score_y);
+        } else {
+            let left_score = players[0].points.to_string();
+            hud.scores.extend(crate::text::layout(
+                &left_score,
+                width / 2.0 - MARGIN * 2.0 - crate::text::width(&left_score, SCORE_SCALE),
+                score_y,
+                SCORE_SCALE,
+            ));
+            hud.scores.extend(crate::text::layout(
+                &players[1].points.to_string(),
+                width / 2.0 + MARGIN * 2.0,
+                score_y,
+                SCORE_SCALE,
+            ));
+
+            if controller.rules().best_of > 1 {
+                let sets = format!("SETS {}-{}", players[0].sets, players[1].sets);
+                hud.centered(&sets, info_y, width);
+                info_y -= line;
+            }
+        }
+
+        // Only rallies that have sped a ball up show the fastest one.
+        let speed = controller
+            .balls
+            .iter()
+            .map(|ball| ball.speed)
+            .fold(0.0, f32::max);
+        let speedup = speed / controller.config().ball_speed;
+        let in_play = matches!(
+            controller.state(),
+            crate::rules::MatchState::Rally | crate::rules::MatchState::PointScored { .. }
+        );
+        if speedup > 1.0 && in_play {
+            hud.centered(&format!("SPEED {:.2}X", speedup), info_y, width);
+        }
+
+        let message_y = height / 4.0;
+        match controller.state() {
+            crate::rules::MatchState::Serving { .. } => {
+                if let Some(prompt) = serve_prompt {
+                    hud.centered(&format!("{} TO SERVE", prompt), message_y, width);
+                }
+            }
+            crate::rules::MatchState::PointScored { scorer, .. } => {
+                hud.centered(&format!("PLAYER {} SCORES", scorer + 1), message_y, width);
+            }
+            crate::rules::MatchState::LifeLost { player, .. } => {
+                let message = if players[player].eliminated() {
+                    format!("PLAYER {} IS OUT", player + 1)
+                } else {
+                    format!("PLAYER {} LOSES A LIFE", player + 1)
+                };
+                hud.centered(&message, message_y, width);
+            }
+            crate::rules::MatchState::GameOver { winner } => {
+                hud.centered(&format!("PLAYER {} WINS!", winner + 1), message_y, width);
+                if let Some(prompt) = serve_prompt {
+                    let message = format!("{} TO PLAY AGAIN", prompt);
+                    hud.centered(&message, message_y - line, width);
+                }
+            }
+            _ => (),
+        }
+
+        hud
+    }
+
+    // Each player still in shows their lives next to their side, the top
+    // one level with the scores of a two player match.
+    fn lives(&mut self, controller: &crate::pong::Controller, top_y: f32) {
+        let size = controller.size();
+        let width = size.width as f32;
+        let height = size.height as f32;
+        let text_height = crate::text::height(SCORE_SCALE);
+
+        for player in controller.players.iter().filter(|p| !p.eliminated()) {
+            let lives = player.lives.to_string();
+            let text_width = crate::text::width(&lives, SCORE_SCALE);
+            let (x, y) = match player.side {
+                crate::pong::Side::Left => (MARGIN * 2.0, (height - text_height) / 2.0),
+                crate::pong::Side::Right => (
+                    width - MARGIN * 2.0 - text_width,
+                    (height - text_height) / 2.0,
+                ),
+                crate::pong::Side::Bottom => ((width - text_width) / 2.0, MARGIN * 3.0),
+                crate::pong::Side::Top => ((width - text_width) / 2.0, top_y),
+            };
+            self.scores
+                .extend(crate::text::layout(&lives, x, y, SCORE_SCALE));
+        }
+    }
+
+    pub fn status(&mut self, text: &str, controller: &crate::pong::Controller) {
+        self.centered(text, MARGIN, controller.size().width as f32);
+    }
+
+    fn centered(&mut self, text: &str, y: f32, width: f32) {
+        self.messages.extend(crate::text::layout(
+            text,
+            (width - crate::text::width(text, MESSAGE_SCALE)) / 2.0,
+            y,
+            MESSAGE_SCALE,
+        ));
+    }
+}
@@ -0,0 +1,297 @@
+// Levels shipped with the game, looked up by name before trying a file.
+pub const BUNDLED: [(&str, &str); 3] = [
+    ("pillars", include_str!("../levels/pillars.toml")),
+    ("gates", include_str!("../levels/gates.toml")),
+    ("fortress", include_str!("../levels/fortress.toml")),
+];
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for LevelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelError::Io(e) => write!(f, "level i/o error: {}", e),
+            LevelError::Parse(e) => write!(f, "invalid level file: {}", e),
+            LevelError::Invalid(reason) => write!(f, "invalid level: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(e: std::io::Error) -> Self {
+        LevelError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for LevelError {
+    fn from(e: toml::de::Error) -> Self {
+        LevelError::Parse(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Obstacle {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // Positions the obstacle moves through in turn, at `speed` arena units
+    // per second, before heading back to where it started.
+    #[serde(default)]
+    pub path: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub speed: f32,
+}
+
+impl Obstacle {
+    fn waypoints(&self) -> Vec<crate::pong::Vector2D> {
+        std::iter::once([self.x, self.y])
+            .chain(self.path.iter().copied())
+            .map(|[x, y]| crate::pong::Vector2D::new(x, y))
+            .collect()
+    }
+
+    // Where the obstacle is once it has been moving for `time` seconds.
+    pub fn position(&self, time: f32) -> crate::pong::Vector2D {
+        let waypoints = self.waypoints();
+        let segments: Vec<_> = waypoints
+            .iter()
+            .zip(waypoints.iter().cycle().skip(1))
+            .map(|(from, to)| (from, to, distance(from, to)))
+            .collect();
+        let length: f32 = segments.iter().map(|(_, _, length)| length).sum();
+        if self.speed == 0.0 || length == 0.0 {
+            return waypoints[0];
+        }
+
+        let mut travelled = (self.speed * time).rem_euclid(length);
+        for (from, to, length) in segments {
+            if travelled < length {
+                return from.lerp(to, travelled / length);
+            }
+            travelled -= length;
+        }
+        waypoints[0]
+    }
+
+    pub fn aabb(&self, time: f32) -> crate::collision::Aabb {
+        let position = self.position(time);
+        crate::collision::Aabb::new(position.x, position.y, self.width, self.height)
+    }
+
+    // Covers the obstacle wherever it is along its path.
+    pub fn bounds(&self) -> crate::collision::Aabb {
+        let waypoints = self.waypoints();
+        let min_x = waypoints
+            .iter()
+            .map(|point| point.x)
+            .fold(f32::MAX, f32::min);
+        let min_y = waypoints
+            .iter()
+            .map(|point| point.y)
+            .fold(f32::MAX, f32::min);
+        let max_x = waypoints
+            .iter()
+            .map(|point| point.x)
+            .fold(f32::MIN, f32::max);
+        let max_y = waypoints
+            .iter()
+            .map(|point| point.y)
+            .fold(f32::MIN, f32::max);
+
+        crate::collision::Aabb::new(
+            min_x,
+            min_y,
+            max_x - min_x + self.width,
+            max_y - min_y + self.height,
+        )
+    }
+}
+
+fn distance(from: &crate::pong::Vector2D, to: &crate::pong::Vector2D) -> f32 {
+    ((to.x - from.x).powf(2.0) + (to.y - from.y).powf(2.0)).sqrt()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Goal {
+    pub side: crate::pong::Side,
+    // Stretch of the side, in arena units from its bottom or left end.
+    pub start: f32,
+    pub end: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    #[serde(default)]
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    // Once a side has goals, balls only leave through them and bounce off
+    // the rest of it.
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            width: 800,
+            height: 600,
+            obstacles: Vec::new(),
+            goals: Vec::new(),
+        }
+    }
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, LevelError> {
+        Level::parse(&std::fs::read_to_string(path)?)
+    }
+
+    // Bundled levels go by name, anything else is read as a file.
+    pub fn find(name: &str) -> Result<Self, LevelError> {
+        match BUNDLED.iter().find(|(bundled, _)| *bundled == name) {
+            Some((_, text)) => Level::parse(text),
+            None => Level::load(std::path::Path::new(name)),
+        }
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        winit::dpi::PhysicalSize::new(self.width, self.height)
+    }
+
+    // Stretches of a side outside its goals, from its bottom or left end.
+    // Sides without goals block nothing.
+    pub fn blocked(&self, side: crate::pong::Side) -> Vec<(f32, f32)> {
+        let mut goals: Vec<(f32, f32)> = self
+            .goals
+            .iter()
+            .filter(|goal| goal.side == side)
+            .map(|goal| (goal.start, goal.end))
+            .collect();
+        if goals.is_empty() {
+            return Vec::new();
+        }
+        goals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let size = crate::pong::Vector2D::new(self.width as f32, self.height as f32);
+        let length = side.axis().split(size).1;
+        let mut blocked = Vec::new();
+        let mut from = 0.0;
+        for (start, end) in goals {
+            if start > from {
+                blocked.push((from, start));
+            }
+            from = f32::max(from, end);
+        }
+        if from < length {
+            blocked.push((from, length));
+        }
+        blocked
+    }
+
+    // Obstacles must stay inside the arena and clear of the lanes of the
+    // paddles in play, wherever they are along their paths.
+    pub fn validate(
+        &self,
+        config: &crate::config::GameConfig,
+        players: usize,
+    ) -> Result<(), LevelError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(LevelError::Invalid(
+                "the arena must not be empty".to_string(),
+            ));
+        }
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let arena = crate::collision::Aabb::new(0.0, 0.0, width, height);
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            let number = index + 1;
+            let sizes = [obstacle.x, obstacle.y, obstacle.width, obstacle.height];
+            if obstacle.width <= 0.0
+                || obstacle.height <= 0.0
+                || sizes.iter().any(|value| !value.is_finite())
+            {
+                return Err(LevelError::Invalid(format!(
+                    "obstacle {} must have a size above zero",
+                    number
+                )));
+            }
+            if !(obstacle.speed >= 0.0 && obstacle.speed.is_finite()) {
+                return Err(LevelError::Invalid(format!(
+                    "obstacle {} must not have a negative speed",
+                    number
+                )));
+            }
+
+            let bounds = obstacle.bounds();
+            if bounds.x < arena.x
+                || bounds.y < arena.y
+                || bounds.x + bounds.width > arena.width
+                || bounds.y + bounds.height > arena.height
+                || !bounds.width.is_finite()
+                || !bounds.height.is_finite()
+            {
+                return Err(LevelError::Invalid(format!(
+                    "obstacle {} leaves the {}x{} arena",
+                    number, self.width, self.height
+                )));
+            }
+
+            for (player, side) in crate::pong::Side::ALL[..players].iter().enumerate() {
+                let depth = config.paddle_width;
+                let lane = match side {
+                    crate::pong::Side::Left => crate::collision::Aabb::new(0.0, 0.0, depth, height),
+                    crate::pong::Side::Right => {
+                        crate::collision::Aabb::new(width - depth, 0.0, depth, height)
+                    }
+                    crate::pong::Side::Bottom => {
+                        crate::collision::Aabb::new(0.0, 0.0, width, depth)
+                    }
+                    crate::pong::Side::Top => {
+                        crate::collision::Aabb::new(0.0, height - depth, width, depth)
+                    }
+                };
+                if bounds.overlaps(&lane) {
+                    return Err(LevelError::Invalid(format!(
+                        "obstacle {} overlaps the paddle lane of player {}",
+                        number,
+                        player + 1
+                    )));
+                }
+            }
+        }
+
+        for (index, goal) in self.goals.iter().enumerate() {
+            let size = crate::pong::Vector2D::new(width, height);
+            let length = goal.side.axis().split(size).1;
+            if !(0.0 <= goal.start && goal.start < goal.end && goal.end <= length) {
+                return Err(LevelError::Invalid(format!(
+                    "goal {} must span part of a side {} long",
+                    index + 1,
+                    length
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
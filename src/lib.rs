@@ -0,0 +1,24 @@
+pub mod ai;
+pub mod batch;
+pub mod bindings;
+pub mod collision;
+pub mod config;
+pub mod controls;
+pub mod engine;
+pub mod event;
+pub mod file;
+pub mod gamepad;
+pub mod headless;
+pub mod hud;
+pub mod level;
+pub mod net;
+pub mod pong;
+pub mod replay;
+pub mod rules;
+pub mod screenshot;
+pub mod snapshot;
+pub mod text;
+pub mod timestep;
+pub mod vertex;
+pub mod viewport;
+pub mod window;
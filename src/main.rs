@@ -1,9 +1,299 @@
-mod engine;
-mod pong;
-mod vertex;
-mod window;
+mod args;
 
 fn main() {
-    let mut window = window::Window::new();
+    let args = match args::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let seed = args.seed.unwrap_or_else(rand::random);
+
+    let replay = args.replay.as_ref().map(|path| {
+        pong::replay::Replay::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    let snapshot = args.snapshot.as_ref().map(|path| {
+        pong::snapshot::Snapshot::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    let net = args.net();
+    let transport = net.as_ref().map(|net| {
+        net.connect(seed).unwrap_or_else(|e| {
+            eprintln!("{}: {}", net.bind, e);
+            std::process::exit(1);
+        })
+    });
+
+    if args.headless {
+        if let (Some(net), Some(transport)) = (&net, transport) {
+            simulate_net(&args, seed, snapshot.as_ref(), net, transport);
+            return;
+        }
+
+        match (&args.screenshot, replay) {
+            (Some(path), replay) => render_headless(&args, seed, replay, snapshot, path),
+            (None, Some(replay)) => simulate_replay(&args, &replay),
+            (None, None) => simulate_headless(&args, seed, snapshot.as_ref()),
+        }
+        return;
+    }
+
+    if replay.is_none() {
+        println!("seed {}", seed);
+    }
+    let mut window = pong::window::Window::new(
+        pong::engine::Options {
+            seed,
+            tick_rate: args.tick_rate,
+            arena: args.arena,
+            rules: args.rules,
+            config: args.config.game.clone(),
+            level: args.level.clone(),
+            display: args.config.display.clone(),
+            input: args.config.input.clone(),
+            bindings: args.config.bindings.clone(),
+            players: args.players,
+            replay,
+            net,
+            snapshot,
+        },
+        transport,
+    );
     window.run();
 }
+
+fn simulate_headless(args: &args::Args, seed: u64, snapshot: Option<&pong::snapshot::Snapshot>) {
+    let mut simulation = pong::headless::Simulation::new(
+        args.arena,
+        args.dt,
+        seed,
+        args.rules.clone(),
+        args.config.game.clone(),
+        args.level.clone(),
+    );
+    if let Some(snapshot) = snapshot {
+        simulation.controller.restore(snapshot);
+    }
+    let players = &simulation.controller.players;
+    let mut controls: Vec<_> = (0..players.len())
+        .map(|player| args.players[player].build(player, seed, &args.config.input))
+        .collect();
+    simulation.run_controlled(&mut controls, args.ticks);
+
+    let players = &simulation.controller.players;
+    let standing = if simulation.controller.rules().uses_lives() {
+        format!(
+            "lives {}",
+            dashed(players.iter().map(|player| player.lives))
+        )
+    } else {
+        format!(
+            "current set {}",
+            dashed(players.iter().map(|player| player.points))
+        )
+    };
+    println!(
+        "seed {}: {} ticks simulated, matches {}, {}",
+        seed,
+        simulation.ticks,
+        dashed(simulation.matches_won[..players.len()].iter().copied()),
+        standing
+    );
+    println!("{:?}", simulation.stats);
+    save_snapshot(args, &simulation.controller);
+}
+
+// Plays the local paddle with its configured controls against a peer until
+// the first `--ticks` ticks are confirmed, then prints a checksum of the
+// resulting state that must match the one printed by the peer.
+fn simulate_net(
+    args: &args::Args,
+    seed: u64,
+    snapshot: Option<&pong::snapshot::Snapshot>,
+    net: &pong::net::NetOptions,
+    transport: Box<dyn pong::net::Transport>,
+) {
+    let mut controller = pong::pong::Controller::new(
+        &args.arena,
+        seed,
+        args.rules.clone(),
+        args.config.game.clone(),
+        args.level.clone(),
+    );
+    if let Some(snapshot) = snapshot {
+        controller.restore(snapshot);
+    }
+    let mut session = pong::net::Session::new(
+        transport,
+        &controller,
+        net.player,
+        net.delay,
+        args.tick_rate,
+    );
+    let mut control = args.players[net.player].build(net.player, seed, &args.config.input);
+    let timeout = std::time::Duration::from_secs(10);
+    let mut last_progress = std::time::Instant::now();
+    let mut progress = (0, 0);
+
+    while session.tick() < args.ticks || session.confirmed() < args.ticks {
+        let result = if session.tick() < args.ticks {
+            let input = control.input(net.player, &controller, args.dt);
+            if let pong::rules::MatchState::Serving { server } = controller.state() {
+                if server == net.player && control.serve(net.player, &controller, args.dt) {
+                    session.serve();
+                }
+            }
+            session.advance(&mut controller, input).map(|_| ())
+        } else {
+            session.poll(&mut controller).and_then(|()| session.send())
+        };
+        if let Err(e) = result {
+            eprintln!("network error: {}", e);
+            std::process::exit(1);
+        }
+
+        if (session.tick(), session.confirmed()) != progress {
+            progress = (session.tick(), session.confirmed());
+            last_progress = std::time::Instant::now();
+        } else if last_progress.elapsed() > timeout {
+            eprintln!("peer stopped responding at tick {}", session.confirmed());
+            std::process::exit(1);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    // Keep resending until the peer has our commands too, but do not wait
+    // forever for an acknowledgement that may have been lost.
+    let linger = std::time::Instant::now();
+    while session.acked() < args.ticks && linger.elapsed() < std::time::Duration::from_secs(2) {
+        if let Err(e) = session.poll(&mut controller).and_then(|()| session.send()) {
+            eprintln!("network error: {}", e);
+            std::process::exit(1);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    let players = &controller.players;
+    println!(
+        "seed {}: {} ticks synced, {} rollbacks, points {}-{}, checksum {:016x}",
+        seed,
+        session.tick(),
+        session.rollbacks,
+        players[0].points,
+        players[1].points,
+        controller.snapshot().checksum()
+    );
+    save_snapshot(args, &controller);
+}
+
+fn simulate_replay(args: &args::Args, replay: &pong::replay::Replay) {
+    let mut simulation = pong::headless::Simulation::from_replay(replay);
+    for tick in replay.ticks.iter().take(args.ticks as usize) {
+        simulation.step_replay(*tick);
+    }
+
+    let players = &simulation.controller.players;
+    let standing = if simulation.controller.rules().uses_lives() {
+        format!(
+            "lives {}",
+            dashed(players.iter().map(|player| player.lives))
+        )
+    } else {
+        format!(
+            "sets {}, points {}",
+            dashed(players.iter().map(|player| player.sets)),
+            dashed(players.iter().map(|player| player.points))
+        )
+    };
+    println!(
+        "seed {}: {} ticks replayed, {:?}, {}",
+        replay.header.seed,
+        simulation.ticks,
+        simulation.controller.state(),
+        standing
+    );
+    println!("{:?}", simulation.stats);
+    save_snapshot(args, &simulation.controller);
+}
+
+// Joins per player numbers like "3-1".
+fn dashed(values: impl Iterator<Item = u32>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn save_snapshot(args: &args::Args, controller: &pong::pong::Controller) {
+    let Some(path) = &args.save_snapshot else {
+        return;
+    };
+
+    if let Err(e) = controller.snapshot().save(path) {
+        eprintln!("{}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    println!("saved snapshot to {}", path.display());
+}
+
+fn render_headless(
+    args: &args::Args,
+    seed: u64,
+    replay: Option<pong::replay::Replay>,
+    snapshot: Option<pong::snapshot::Snapshot>,
+    path: &std::path::Path,
+) {
+    let size = replay
+        .as_ref()
+        .map_or(args.arena, |replay| replay.header.size);
+    let options = pong::engine::Options {
+        seed,
+        tick_rate: args.tick_rate,
+        arena: size,
+        rules: args.rules.clone(),
+        config: args.config.game.clone(),
+        level: args.level.clone(),
+        display: args.config.display.clone(),
+        input: args.config.input.clone(),
+        bindings: args.config.bindings.clone(),
+        players: args.players,
+        replay,
+        net: None,
+        snapshot,
+    };
+
+    let mut engine =
+        match futures::executor::block_on(pong::engine::Engine::new_headless(size, &options)) {
+            Ok(engine) => engine,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+
+    for _ in 0..args.ticks {
+        engine.tick();
+    }
+    engine.build_frame();
+
+    if let Err(e) = engine.save_screenshot(path) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    println!(
+        "seed {}: saved frame after {} ticks to {}",
+        seed,
+        args.ticks,
+        path.display()
+    );
+}
@@ -0,0 +1,433 @@
+const MAGIC: &[u8; 4] = b"PNET";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 8 + 8 + 8 + 2;
+const MAX_COMMANDS_PER_PACKET: usize = 256;
+pub const MAX_ROLLBACK: u64 = 16;
+pub const DEFAULT_INPUT_DELAY: u64 = 2;
+pub const MAX_INPUT_DELAY: u64 = 30;
+
+pub trait Transport {
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()>;
+    fn recv(&mut self, buffer: &mut [u8]) -> std::io::Result<Option<usize>>;
+}
+
+pub struct UdpTransport {
+    socket: std::net::UdpSocket,
+}
+
+impl UdpTransport {
+    pub fn connect(
+        bind: std::net::SocketAddr,
+        peer: std::net::SocketAddr,
+    ) -> std::io::Result<Self> {
+        let socket = std::net::UdpSocket::bind(bind)?;
+        socket.connect(peer)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    // The peer may not be listening yet, which loopback reports as a refused
+    // connection on the next call. That is not an error for a lossy protocol.
+    fn ignore_refused<T>(result: std::io::Result<T>, default: T) -> std::io::Result<T> {
+        match result {
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::ConnectionRefused =>
+            {
+                Ok(default)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        UdpTransport::ignore_refused(self.socket.send(packet).map(|_| ()), ())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> std::io::Result<Option<usize>> {
+        UdpTransport::ignore_refused(self.socket.recv(buffer).map(Some), None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conditions {
+    pub latency: std::time::Duration,
+    pub jitter: std::time::Duration,
+    pub loss: f32,
+}
+
+// Wraps another transport and delays, reorders and drops outgoing packets so
+// rollback can be exercised on loopback.
+pub struct LossyTransport<T: Transport> {
+    inner: T,
+    conditions: Conditions,
+    rng: rand_chacha::ChaCha8Rng,
+    queue: Vec<(std::time::Instant, Vec<u8>)>,
+}
+
+impl<T: Transport> LossyTransport<T> {
+    pub fn new(inner: T, conditions: Conditions, seed: u64) -> Self {
+        Self {
+            inner,
+            conditions,
+            rng: rand::SeedableRng::seed_from_u64(seed),
+            queue: Vec::new(),
+        }
+    }
+
+    fn deliver(&mut self) -> std::io::Result<()> {
+        let now = std::time::Instant::now();
+        let mut i = 0;
+        while i < self.queue.len() {
+            if self.queue[i].0 <= now {
+                let (_, packet) = self.queue.swap_remove(i);
+                self.inner.send(&packet)?;
+            } else {
+                i += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        use rand::Rng;
+
+        if self.rng.gen::<f32>() >= self.conditions.loss {
+            let jitter = self.conditions.jitter.mul_f32(self.rng.gen());
+            let due = std::time::Instant::now() + self.conditions.latency + jitter;
+            self.queue.push((due, packet.to_vec()));
+        }
+        self.deliver()
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> std::io::Result<Option<usize>> {
+        self.deliver()?;
+        self.inner.recv(buffer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetOptions {
+    pub bind: std::net::SocketAddr,
+    pub peer: std::net::SocketAddr,
+    pub player: usize,
+    pub delay: u64,
+    pub conditions: Conditions,
+}
+
+impl NetOptions {
+    pub fn connect(&self, seed: u64) -> std::io::Result<Box<dyn Transport>> {
+        let transport = UdpTransport::connect(self.bind, self.peer)?;
+        if self.conditions == Conditions::default() {
+            Ok(Box::new(transport))
+        } else {
+            Ok(Box::new(LossyTransport::new(
+                transport,
+                self.conditions,
+                seed ^ self.player as u64,
+            )))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Command {
+    pub input: crate::pong::Input,
+    pub serve: bool,
+}
+
+impl Command {
+    pub fn encode(self, bytes: &mut Vec<u8>) {
+        bytes.push(self.input.to_bits() | (self.serve as u8) << 3);
+        bytes.extend(self.input.payload());
+    }
+
+    // Decodes one command from the front of `bytes`, returning the rest.
+    pub fn decode(bytes: &[u8]) -> Option<(Self, &[u8])> {
+        let (&byte, rest) = bytes.split_first()?;
+        let bits = byte & 0b111;
+        let len = crate::pong::Input::payload_len(bits);
+        let input = crate::pong::Input::from_bits(bits, rest.get(..len)?)?;
+
+        Some((
+            Self {
+                input,
+                serve: byte & 1 << 3 != 0,
+            },
+            &rest[len..],
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Packet {
+    session: u64,
+    ack: u64,
+    start: u64,
+    commands: Vec<Command>,
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 3 * self.commands.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.session.to_le_bytes());
+        bytes.extend_from_slice(&self.ack.to_le_bytes());
+        bytes.extend_from_slice(&self.start.to_le_bytes());
+        bytes.extend_from_slice(&(self.commands.len() as u16).to_le_bytes());
+        for command in &self.commands {
+            command.encode(&mut bytes);
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC || bytes[4] != VERSION {
+            return None;
+        }
+
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let count = u16::from_le_bytes([bytes[29], bytes[30]]) as usize;
+        let mut rest = &bytes[HEADER_LEN..];
+        let mut commands = Vec::with_capacity(count.min(MAX_COMMANDS_PER_PACKET));
+        for _ in 0..count {
+            let (command, tail) = Command::decode(rest)?;
+            commands.push(command);
+            rest = tail;
+        }
+
+        Some(Self {
+            session: u64_at(5),
+            ack: u64_at(13),
+            start: u64_at(21),
+            commands,
+        })
+    }
+}
+
+// Runs one shared match between two peers. Every tick is simulated with the
+// local command and either the confirmed or a predicted remote command; when
+// a confirmed command disagrees with its prediction the controller is rolled
+// back to that tick and re-simulated.
+pub struct Session {
+    transport: Box<dyn Transport>,
+    session: u64,
+    local: usize,
+    dt: f32,
+    tick: u64,
+    serve_requested: bool,
+    local_commands: Vec<Command>,
+    remote_commands: Vec<Option<Command>>,
+    predicted: Vec<Command>,
+    confirmed: u64,
+    remote_ack: u64,
+    snapshot_base: u64,
+    snapshots: std::collections::VecDeque<crate::snapshot::Snapshot>,
+    pub rollbacks: u64,
+    pub ignored_packets: u64,
+}
+
+impl Session {
+    pub fn new(
+        transport: Box<dyn Transport>,
+        controller: &crate::pong::Controller,
+        local: usize,
+        delay: u64,
+        tick_rate: f32,
+    ) -> Self {
+        // Peers only talk to each other when they start from the same match.
+        let session = controller.snapshot().checksum() ^ tick_rate.to_bits() as u64;
+
+        Self {
+            transport,
+            session,
+            local,
+            dt: 1.0 / tick_rate,
+            tick: 0,
+            serve_requested: false,
+            local_commands: vec![Command::default(); delay as usize],
+            remote_commands: Vec::new(),
+            predicted: Vec::new(),
+            confirmed: 0,
+            remote_ack: 0,
+            snapshot_base: 0,
+            snapshots: std::collections::VecDeque::new(),
+            rollbacks: 0,
+            ignored_packets: 0,
+        }
+    }
+
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn confirmed(&self) -> u64 {
+        self.confirmed
+    }
+
+    pub fn stalled(&self) -> bool {
+        self.tick >= self.confirmed + MAX_ROLLBACK
+    }
+
+    // Number of local commands the peer has confirmed receiving.
+    pub fn acked(&self) -> u64 {
+        self.remote_ack
+    }
+
+    pub fn serve(&mut self) {
+        self.serve_requested = true;
+    }
+
+    pub fn advance(
+        &mut self,
+        controller: &mut crate::pong::Controller,
+        input: crate::pong::Input,
+    ) -> std::io::Result<bool> {
+        self.poll(controller)?;
+
+        let advanced = !self.stalled();
+        if advanced {
+            self.local_commands.push(Command {
+                input,
+                serve: std::mem::take(&mut self.serve_requested),
+            });
+            self.simulate(controller, self.tick);
+            self.tick += 1;
+        }
+
+        self.send()?;
+        Ok(advanced)
+    }
+
+    pub fn poll(&mut self, controller: &mut crate::pong::Controller) -> std::io::Result<()> {
+        let mut buffer = [0; 2048];
+        let mut rollback: Option<u64> = None;
+
+        while let Some(len) = self.transport.recv(&mut buffer)? {
+            let Some(packet) = Packet::decode(&buffer[..len]) else {
+                self.ignored_packets += 1;
+                continue;
+            };
+            if packet.session != self.session {
+                self.ignored_packets += 1;
+                continue;
+            }
+
+            self.remote_ack = self.remote_ack.max(packet.ack);
+            for (i, command) in packet.commands.into_iter().enumerate() {
+                // A well behaved peer never runs further ahead than its own
+                // stall limit plus input delay.
+                let tick = packet.start + i as u64;
+                let limit = self.local_commands.len() as u64 + MAX_ROLLBACK + MAX_INPUT_DELAY;
+                if tick < self.confirmed || tick >= limit {
+                    continue;
+                }
+
+                let index = tick as usize;
+                if self.remote_commands.len() <= index {
+                    self.remote_commands.resize(index + 1, None);
+                }
+                if self.remote_commands[index].is_some() {
+                    continue;
+                }
+                self.remote_commands[index] = Some(command);
+
+                if tick < self.tick && self.predicted[index] != command {
+                    rollback = Some(rollback.map_or(tick, |earliest| earliest.min(tick)));
+                }
+            }
+        }
+
+        while let Some(Some(_)) = self.remote_commands.get(self.confirmed as usize) {
+            self.confirmed += 1;
+        }
+
+        if let Some(from) = rollback {
+            self.rollbacks += 1;
+            controller.restore(&self.snapshots[(from - self.snapshot_base) as usize]);
+            controller.without_listeners(|controller| {
+                for tick in from..self.tick {
+                    self.simulate(controller, tick);
+                }
+            });
+        }
+
+        // Only ticks from the first unconfirmed one onward can be rolled back.
+        while self.snapshot_base < self.confirmed.min(self.tick) {
+            self.snapshots.pop_front();
+            self.snapshot_base += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn send(&mut self) -> std::io::Result<()> {
+        let start = self.remote_ack.min(self.local_commands.len() as u64);
+        let end = self
+            .local_commands
+            .len()
+            .min(start as usize + MAX_COMMANDS_PER_PACKET);
+        let packet = Packet {
+            session: self.session,
+            ack: self.confirmed,
+            start,
+            commands: self.local_commands[start as usize..end].to_vec(),
+        };
+        self.transport.send(&packet.encode())
+    }
+
+    fn remote_command(&self, tick: u64) -> Command {
+        if let Some(Some(command)) = self.remote_commands.get(tick as usize) {
+            return *command;
+        }
+
+        // Predict that the remote player keeps holding the last confirmed
+        // input, and never predict a serve.
+        let input = match self.confirmed {
+            0 => crate::pong::Input::None,
+            confirmed => self.remote_commands[confirmed as usize - 1].unwrap().input,
+        };
+        Command {
+            input,
+            serve: false,
+        }
+    }
+
+    fn simulate(&mut self, controller: &mut crate::pong::Controller, tick: u64) {
+        let index = (tick - self.snapshot_base) as usize;
+        self.snapshots.truncate(index);
+        self.snapshots.push_back(controller.snapshot());
+
+        let remote = self.remote_command(tick);
+        self.predicted.truncate(tick as usize);
+        self.predicted.push(remote);
+
+        let mut commands = [Command::default(); 2];
+        commands[self.local] = self.local_commands[tick as usize];
+        commands[1 - self.local] = remote;
+
+        for (player, command) in commands.iter().enumerate() {
+            controller.players[player].input = command.input;
+        }
+        match controller.state() {
+            crate::rules::MatchState::Serving { server } if commands[server].serve => {
+                controller.serve()
+            }
+            crate::rules::MatchState::GameOver { .. } if commands.iter().any(|c| c.serve) => {
+                controller.reset_match()
+            }
+            _ => (),
+        }
+
+        controller.update(self.dt);
+    }
+}
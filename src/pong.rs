@@ -1,12 +1,16 @@
 use rand::prelude::Distribution;
+use rand::{Rng, SeedableRng};
 
-const PADDLE_WIDTH: f32 = 30.0;
-const PADDLE_HEIGHT: f32 = 100.0;
-const PADDLE_SPEED: f32 = 500.0;
-const BALL_SIZE: f32 = 20.0;
-const BALL_SPEED: f32 = 400.0;
+const MAX_COLLISIONS_PER_STEP: usize = 8;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+enum Collider {
+    Wall,
+    Obstacle(usize),
+    Player(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Vector2D {
     pub x: f32,
     pub y: f32,
@@ -25,253 +29,1385 @@ impl Vector2D {
             y: self.y / norm,
         }
     }
+
+    pub fn lerp(&self, to: &Vector2D, t: f32) -> Self {
+        Self {
+            x: self.x + (to.x - self.x) * t,
+            y: self.y + (to.y - self.y) * t,
+        }
+    }
 }
 
-#[derive(Debug)]
+// Main direction a ball travels in, which its angle and spin are measured
+// against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Axis {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    // Splits a vector into its parts along and across the axis.
+    pub fn split(self, vector: Vector2D) -> (f32, f32) {
+        match self {
+            Axis::Horizontal => (vector.x, vector.y),
+            Axis::Vertical => (vector.y, vector.x),
+        }
+    }
+
+    pub fn join(self, along: f32, across: f32) -> Vector2D {
+        match self {
+            Axis::Horizontal => Vector2D::new(along, across),
+            Axis::Vertical => Vector2D::new(across, along),
+        }
+    }
+}
+
+// Sides of the arena, in the order players take them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Bottom, Side::Top];
+
+    // Points from the side into the arena.
+    pub fn normal(self) -> Vector2D {
+        match self {
+            Side::Left => Vector2D::new(1.0, 0.0),
+            Side::Right => Vector2D::new(-1.0, 0.0),
+            Side::Bottom => Vector2D::new(0.0, 1.0),
+            Side::Top => Vector2D::new(0.0, -1.0),
+        }
+    }
+
+    // Balls leave a paddle on this side along this axis, and the paddle
+    // slides across it.
+    pub fn axis(self) -> Axis {
+        match self {
+            Side::Left | Side::Right => Axis::Horizontal,
+            Side::Bottom | Side::Top => Axis::Vertical,
+        }
+    }
+
+    pub fn facing(normal: &Vector2D) -> Side {
+        if normal.x > 0.0 {
+            Side::Left
+        } else if normal.x < 0.0 {
+            Side::Right
+        } else if normal.y < 0.0 {
+            Side::Top
+        } else {
+            Side::Bottom
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Player {
+    pub side: Side,
     pub position: Vector2D,
-    pub vertices: [[f32; 3]; 4],
+    pub width: f32,
+    pub height: f32,
     pub input: Input,
+    // Speed along the side over the last update, in arena units per second.
+    pub velocity: f32,
     pub points: u32,
+    pub sets: u32,
+    // Only lost when playing for lives, a player without any is out.
+    pub lives: u32,
 }
 
 impl Player {
-    pub fn new(x: f32, y: f32, size: &winit::dpi::PhysicalSize<u32>) -> Self {
+    pub fn new(side: Side, x: f32, y: f32, width: f32, height: f32, lives: u32) -> Self {
         Self {
+            side,
             position: Vector2D::new(x, y),
-            vertices: Player::move_vertices([x, y], size),
+            width,
+            height,
             input: Input::None,
+            velocity: 0.0,
             points: 0,
+            sets: 0,
+            lives,
         }
     }
 
-    pub fn move_position(&mut self, x: f32, y: f32, size: &winit::dpi::PhysicalSize<u32>) {
+    pub fn eliminated(&self) -> bool {
+        self.lives == 0
+    }
+
+    // Extent of the paddle along its side.
+    pub fn length(&self) -> f32 {
+        self.side
+            .axis()
+            .split(Vector2D::new(self.width, self.height))
+            .1
+    }
+
+    pub fn move_position(&mut self, x: f32, y: f32) {
         self.position = Vector2D::new(x, y);
-        self.vertices = Player::move_vertices([self.position.x, self.position.y], size);
     }
 
-    pub fn move_vertices(to: [f32; 2], size: &winit::dpi::PhysicalSize<u32>) -> [[f32; 3]; 4] {
-        let mut position: [[f32; 3]; 4] = [[0.0; 3]; 4];
-        position[0] = crate::engine::Engine::screen_space_to_clip_space(&[to[0], to[1], 0.0], size);
-        position[1] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0] + PADDLE_WIDTH, to[1], 0.0],
-            size,
-        );
-        position[2] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0] + PADDLE_WIDTH, to[1] + PADDLE_HEIGHT, 0.0],
-            size,
-        );
-        position[3] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0], to[1] + PADDLE_HEIGHT, 0.0],
-            size,
-        );
+    pub fn center(&self) -> Vector2D {
+        Vector2D::new(
+            self.position.x + self.width / 2.0,
+            self.position.y + self.height / 2.0,
+        )
+    }
+
+    pub fn aabb(&self) -> crate::collision::Aabb {
+        crate::collision::Aabb::new(self.position.x, self.position.y, self.width, self.height)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub players: Vec<Vector2D>,
+    pub balls: Vec<Vector2D>,
+    pub obstacles: Vec<Vector2D>,
+}
+
+impl Frame {
+    pub fn interpolate(&self, next: &Frame, alpha: f32) -> Frame {
+        // Balls only line up between frames while none spawned or scored.
+        let balls = if self.balls.len() == next.balls.len() {
+            self.balls
+                .iter()
+                .zip(&next.balls)
+                .map(|(ball, next)| ball.lerp(next, alpha))
+                .collect()
+        } else {
+            next.balls.clone()
+        };
 
-        position
+        Frame {
+            players: self
+                .players
+                .iter()
+                .zip(&next.players)
+                .map(|(player, next)| player.lerp(next, alpha))
+                .collect(),
+            balls,
+            obstacles: self
+                .obstacles
+                .iter()
+                .zip(&next.obstacles)
+                .map(|(obstacle, next)| obstacle.lerp(next, alpha))
+                .collect(),
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct Controller {
     size: winit::dpi::PhysicalSize<u32>,
-    pub players: [Player; 2],
-    pub ball: Ball,
+    seed: u64,
+    rng: rand_chacha::ChaCha8Rng,
+    rules: crate::rules::Ruleset,
+    config: crate::config::GameConfig,
+    level: crate::level::Level,
+    // Seconds the obstacles have been moving for.
+    level_time: f32,
+    state: crate::rules::MatchState,
+    server: usize,
+    serve_requested: bool,
+    // Paddle hits since the last point or extra ball, and rally time since
+    // the last extra ball, for multi-ball.
+    rally_hits: u32,
+    spawn_timer: f32,
+    events: Vec<crate::event::Event>,
+    listeners: crate::event::Listeners,
+    pub players: Vec<Player>,
+    pub balls: Vec<Ball>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Ball {
     pub position: Vector2D,
-    pub vertices: [[f32; 3]; 4],
+    pub size: f32,
     pub direction: Vector2D,
+    // Arena units per second, raised by every paddle hit of a rally.
+    pub speed: f32,
+    // Sideways pull on the direction, per second. Positive curves upwards,
+    // or rightwards for balls travelling vertically.
+    pub spin: f32,
+    pub travel: Axis,
 }
 
 impl Ball {
-    pub fn new(x: f32, y: f32, direction: Vector2D, size: &winit::dpi::PhysicalSize<u32>) -> Self {
+    pub fn new(x: f32, y: f32, size: f32, direction: Vector2D, speed: f32) -> Self {
         Self {
             position: Vector2D::new(x, y),
-            vertices: Ball::move_vertices([x, y], size),
+            size,
             direction: direction.normalize(),
+            speed,
+            spin: 0.0,
+            travel: Axis::Horizontal,
         }
     }
 
-    pub fn move_position(&mut self, x: f32, y: f32, size: &winit::dpi::PhysicalSize<u32>) {
+    pub fn move_position(&mut self, x: f32, y: f32) {
         self.position = Vector2D::new(x, y);
-        self.vertices = Ball::move_vertices([self.position.x, self.position.y], size)
     }
 
-    pub fn move_vertices(to: [f32; 2], size: &winit::dpi::PhysicalSize<u32>) -> [[f32; 3]; 4] {
-        let mut position: [[f32; 3]; 4] = [[0.0; 3]; 4];
-        position[0] = crate::engine::Engine::screen_space_to_clip_space(&[to[0], to[1], 0.0], size);
-        position[1] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0] + BALL_SIZE, to[1], 0.0],
-            size,
-        );
-        position[2] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0] + BALL_SIZE, to[1] + BALL_SIZE, 0.0],
-            size,
-        );
-        position[3] = crate::engine::Engine::screen_space_to_clip_space(
-            &[to[0], to[1] + BALL_SIZE, 0.0],
-            size,
-        );
+    pub fn center(&self) -> Vector2D {
+        Vector2D::new(
+            self.position.x + self.size / 2.0,
+            self.position.y + self.size / 2.0,
+        )
+    }
 
-        position
+    pub fn aabb(&self) -> crate::collision::Aabb {
+        crate::collision::Aabb::new(self.position.x, self.position.y, self.size, self.size)
     }
 }
 
 impl Controller {
-    pub fn new(size: &winit::dpi::PhysicalSize<u32>) -> Self {
+    pub fn new(
+        size: &winit::dpi::PhysicalSize<u32>,
+        seed: u64,
+        rules: crate::rules::Ruleset,
+        config: crate::config::GameConfig,
+        level: crate::level::Level,
+    ) -> Self {
         let size = winit::dpi::PhysicalSize {
             width: size.width,
             height: size.height,
         };
 
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        let server = rng.gen_range(0..rules.players as u32) as usize;
+
         Self {
             size,
-            players: [
-                Player::new(
-                    0.0,
-                    (size.height as f32 / 2.0) - (PADDLE_HEIGHT / 2.0),
-                    &size,
-                ),
-                Player::new(
-                    size.width as f32 - PADDLE_WIDTH,
-                    (size.height as f32 / 2.0) - (PADDLE_HEIGHT / 2.0),
-                    &size,
-                ),
-            ],
-            ball: Ball::new(
-                (size.width as f32 / 2.0) - (BALL_SIZE / 2.0),
-                (size.height as f32 / 2.0) - (BALL_SIZE / 2.0),
-                Vector2D::new(
-                    rand::distributions::Uniform::new(-1.0, 1.0).sample(&mut rand::thread_rng()),
-                    rand::distributions::Uniform::new(-0.1, 0.1).sample(&mut rand::thread_rng()),
-                ),
-                &size,
+            seed,
+            rng,
+            level,
+            level_time: 0.0,
+            state: crate::rules::MatchState::Serving { server },
+            server,
+            serve_requested: false,
+            rally_hits: 0,
+            spawn_timer: 0.0,
+            events: Vec::new(),
+            listeners: crate::event::Listeners::default(),
+            players: Side::ALL[..rules.players]
+                .iter()
+                .map(|side| Controller::paddle(*side, &size, &config, rules.lives))
+                .collect(),
+            balls: vec![Controller::centered_ball(&size, &config)],
+            rules,
+            config,
+        }
+    }
+
+    // Paddles start centered on their side, lying along it.
+    fn paddle(
+        side: Side,
+        size: &winit::dpi::PhysicalSize<u32>,
+        config: &crate::config::GameConfig,
+        lives: u32,
+    ) -> Player {
+        let width = size.width as f32;
+        let height = size.height as f32;
+        let (x, y) = match side {
+            Side::Left => (0.0, (height / 2.0) - (config.paddle_height / 2.0)),
+            Side::Right => (
+                width - config.paddle_width,
+                (height / 2.0) - (config.paddle_height / 2.0),
+            ),
+            Side::Bottom => ((width / 2.0) - (config.paddle_height / 2.0), 0.0),
+            Side::Top => (
+                (width / 2.0) - (config.paddle_height / 2.0),
+                height - config.paddle_width,
             ),
+        };
+        let (width, height) = match side.axis() {
+            Axis::Horizontal => (config.paddle_width, config.paddle_height),
+            Axis::Vertical => (config.paddle_height, config.paddle_width),
+        };
+
+        Player::new(side, x, y, width, height, lives)
+    }
+
+    pub fn rules(&self) -> &crate::rules::Ruleset {
+        &self.rules
+    }
+
+    pub fn config(&self) -> &crate::config::GameConfig {
+        &self.config
+    }
+
+    pub fn level(&self) -> &crate::level::Level {
+        &self.level
+    }
+
+    pub fn obstacles(&self) -> Vec<crate::collision::Aabb> {
+        self.level
+            .obstacles
+            .iter()
+            .map(|obstacle| obstacle.aabb(self.level_time))
+            .collect()
+    }
+
+    pub fn state(&self) -> crate::rules::MatchState {
+        self.state
+    }
+
+    pub fn snapshot(&self) -> crate::snapshot::Snapshot {
+        crate::snapshot::Snapshot {
+            size: self.size,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            rules: self.rules.clone(),
+            config: self.config.clone(),
+            level: self.level.clone(),
+            level_time: self.level_time,
+            state: self.state,
+            server: self.server,
+            serve_requested: self.serve_requested,
+            rally_hits: self.rally_hits,
+            spawn_timer: self.spawn_timer,
+            players: self.players.clone(),
+            balls: self.balls.clone(),
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
-        self.input(0, dt);
-        self.input(1, dt);
-        self.ball_update(dt);
+    pub fn restore(&mut self, snapshot: &crate::snapshot::Snapshot) {
+        self.size = snapshot.size;
+        self.seed = snapshot.seed;
+        self.rng = snapshot.rng.clone();
+        self.rules = snapshot.rules.clone();
+        self.config = snapshot.config.clone();
+        self.level = snapshot.level.clone();
+        self.level_time = snapshot.level_time;
+        self.state = snapshot.state;
+        self.server = snapshot.server;
+        self.serve_requested = snapshot.serve_requested;
+        self.rally_hits = snapshot.rally_hits;
+        self.spawn_timer = snapshot.spawn_timer;
+        self.players = snapshot.players.clone();
+        self.balls = snapshot.balls.clone();
+        self.events.clear();
     }
 
-    pub fn ball_update(&mut self, dt: f32) {
-        self.ball.move_position(
-            self.ball.position.x + BALL_SPEED * dt * self.ball.direction.x,
-            self.ball.position.y + BALL_SPEED * dt * self.ball.direction.y,
+    pub fn events(&self) -> &[crate::event::Event] {
+        &self.events
+    }
+
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, crate::event::Event> {
+        self.events.drain(..)
+    }
+
+    pub fn subscribe(&mut self, listener: impl FnMut(&crate::event::Event) + 'static) {
+        self.listeners.push(listener);
+    }
+
+    // Runs `f` with every listener detached, for replaying ticks whose events
+    // were already heard once.
+    pub fn without_listeners<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let listeners = std::mem::take(&mut self.listeners);
+        let result = f(self);
+        self.listeners = listeners;
+        result
+    }
+
+    fn emit(&mut self, event: crate::event::Event) {
+        self.listeners.notify(&event);
+        self.events.push(event);
+    }
+
+    pub fn serve(&mut self) {
+        self.serve_requested = true;
+    }
+
+    pub fn serve_requested(&self) -> bool {
+        self.serve_requested
+    }
+
+    fn start_rally(&mut self) {
+        if let crate::rules::MatchState::Serving { server } = self.state {
+            let side = self.players[server].side;
+            let axis = side.axis();
+            let along = if axis.split(side.normal()).0 > 0.0 {
+                0.0..1.0
+            } else {
+                -1.0..0.0
+            };
+
+            let mut ball = Controller::centered_ball(&self.size, &self.config);
+            ball.direction = axis
+                .join(
+                    rand::distributions::Uniform::from(along).sample(&mut self.rng),
+                    rand::distributions::Uniform::new(-0.1, 0.1).sample(&mut self.rng),
+                )
+                .normalize();
+            ball.travel = axis;
+            let direction = ball.direction;
+
+            self.balls = vec![ball];
+            self.rally_hits = 0;
+            self.spawn_timer = 0.0;
+            self.state = crate::rules::MatchState::Rally;
+            self.emit(crate::event::Event::Serve { direction });
+        }
+    }
+
+    // Starts a fresh match from a seed drawn from the current one, so every
+    // match can be reproduced on its own from Controller::new.
+    pub fn reset_match(&mut self) {
+        let next = Controller::new(
             &self.size,
+            self.rng.gen(),
+            self.rules.clone(),
+            self.config.clone(),
+            self.level.clone(),
         );
+        let listeners = std::mem::take(&mut self.listeners);
 
-        if self.ball.position.y + BALL_SIZE > self.size.height as f32 || self.ball.position.y < 0.0
-        {
-            self.ball.direction.y *= -1.0;
+        *self = next;
+        self.listeners = listeners;
+    }
+
+    fn centered_ball(
+        size: &winit::dpi::PhysicalSize<u32>,
+        config: &crate::config::GameConfig,
+    ) -> Ball {
+        Ball::new(
+            (size.width as f32 / 2.0) - (config.ball_size / 2.0),
+            (size.height as f32 / 2.0) - (config.ball_size / 2.0),
+            config.ball_size,
+            Vector2D::new(1.0, 0.0),
+            config.ball_speed,
+        )
+    }
+
+    // Every ball that leaves the arena scores. The rally goes on while balls
+    // remain, unless the point decided the set.
+    fn point_scored(&mut self, scorer: usize) {
+        self.players[scorer].points += 1;
+        self.rally_hits = 0;
+        self.emit(crate::event::Event::PointScored {
+            player: scorer,
+            score: self.players[scorer].points,
+        });
+
+        let set_won = self
+            .rules
+            .set_won(self.players[scorer].points, self.players[1 - scorer].points);
+        if self.balls.is_empty() || set_won {
+            self.balls.clear();
+            self.state = crate::rules::MatchState::PointScored {
+                scorer,
+                remaining: self.rules.point_delay,
+            };
         }
+    }
 
-        for player in &self.players {
-            let anchor_correction = if self.ball.direction.x.is_sign_positive() {
-                BALL_SIZE
-            } else {
-                0.0
+    // Playing for lives, every ball that leaves the arena costs the player
+    // whose side it left through a life. The last player left wins.
+    fn life_lost(&mut self, player: usize) {
+        self.players[player].lives -= 1;
+        self.rally_hits = 0;
+        self.emit(crate::event::Event::LifeLost {
+            player,
+            lives: self.players[player].lives,
+        });
+
+        let remaining = self.players.iter().filter(|p| !p.eliminated()).count();
+        if self.balls.is_empty() || remaining <= 1 {
+            self.balls.clear();
+            self.state = crate::rules::MatchState::LifeLost {
+                player,
+                remaining: self.rules.point_delay,
             };
+        }
+    }
+
+    // The player who lost the life serves next, or the one after them if
+    // they are out.
+    fn finish_life_lost(&mut self, player: usize) {
+        self.balls = vec![Controller::centered_ball(&self.size, &self.config)];
+
+        let mut remaining = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| !player.eliminated())
+            .map(|(index, _)| index);
+        if let (Some(winner), None) = (remaining.next(), remaining.next()) {
+            self.state = crate::rules::MatchState::GameOver { winner };
+            return;
+        }
 
-            if self.ball.position.x + anchor_correction >= player.position.x
-                && self.ball.position.x <= player.position.x + PADDLE_WIDTH
-                && self.ball.position.y >= player.position.y - BALL_SIZE
-                && self.ball.position.y <= player.position.y + PADDLE_HEIGHT
-            {
-                self.ball.direction.x *= -1.0;
-
-                let bounce_direction = self.ball.position.y - player.position.y;
-                if (25.0..=55.0).contains(&bounce_direction) {
-                    self.ball.direction.y = rand::distributions::Uniform::new(-0.05, 0.05)
-                        .sample(&mut rand::thread_rng());
-                } else if bounce_direction > 55.0 {
-                    self.ball.direction.y =
-                        rand::distributions::Uniform::new(0.3, 1.0).sample(&mut rand::thread_rng());
+        let count = self.players.len();
+        self.server = (0..count)
+            .map(|offset| (player + offset) % count)
+            .find(|&index| !self.players[index].eliminated())
+            .unwrap_or(player);
+        self.state = crate::rules::MatchState::Serving {
+            server: self.server,
+        };
+    }
+
+    fn finish_point(&mut self, scorer: usize) {
+        let opponent = 1 - scorer;
+        self.server = self.rules.next_server(self.server, scorer);
+        self.balls = vec![Controller::centered_ball(&self.size, &self.config)];
+
+        if !self
+            .rules
+            .set_won(self.players[scorer].points, self.players[opponent].points)
+        {
+            self.state = crate::rules::MatchState::Serving {
+                server: self.server,
+            };
+            return;
+        }
+
+        self.players[scorer].sets += 1;
+        if self.players[scorer].sets >= self.rules.sets_to_win() {
+            self.state = crate::rules::MatchState::GameOver { winner: scorer };
+            return;
+        }
+
+        for player in &mut self.players {
+            player.points = 0;
+        }
+        self.state = crate::rules::MatchState::Serving {
+            server: self.server,
+        };
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn frame(&self) -> Frame {
+        Frame {
+            players: self.players.iter().map(|player| player.position).collect(),
+            balls: self.balls.iter().map(|ball| ball.position).collect(),
+            obstacles: self
+                .obstacles()
+                .iter()
+                .map(|obstacle| Vector2D::new(obstacle.x, obstacle.y))
+                .collect(),
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+
+        if self.serve_requested {
+            self.serve_requested = false;
+            self.start_rally();
+        }
+
+        for player in 0..self.players.len() {
+            self.input(player, dt);
+        }
+        self.level_time += dt;
+
+        match self.state {
+            crate::rules::MatchState::Rally => self.rally_update(dt),
+            crate::rules::MatchState::PointScored { scorer, remaining } => {
+                if remaining > dt {
+                    self.state = crate::rules::MatchState::PointScored {
+                        scorer,
+                        remaining: remaining - dt,
+                    };
+                } else {
+                    self.finish_point(scorer);
+                }
+            }
+            crate::rules::MatchState::LifeLost { player, remaining } => {
+                if remaining > dt {
+                    self.state = crate::rules::MatchState::LifeLost {
+                        player,
+                        remaining: remaining - dt,
+                    };
                 } else {
-                    self.ball.direction.y = rand::distributions::Uniform::new(-1.0, -0.3)
-                        .sample(&mut rand::thread_rng());
+                    self.finish_life_lost(player);
                 }
-                self.ball.direction = self.ball.direction.normalize();
             }
+            crate::rules::MatchState::Serving { .. }
+            | crate::rules::MatchState::GameOver { .. } => {}
         }
+    }
 
-        if self.ball.position.x + BALL_SIZE < 0.0 {
-            self.ball = Ball::new(
-                (self.size.width as f32 / 2.0) - (BALL_SIZE / 2.0),
-                (self.size.height as f32 / 2.0) - (BALL_SIZE / 2.0),
-                Vector2D::new(
-                    rand::distributions::Uniform::new(0.0, 1.0).sample(&mut rand::thread_rng()),
-                    rand::distributions::Uniform::new(-0.1, 0.1).sample(&mut rand::thread_rng()),
-                ),
-                &self.size,
-            );
+    fn rally_update(&mut self, dt: f32) {
+        for index in 0..self.balls.len() {
+            self.ball_update(index, dt);
+        }
+        if self.config.ball_collisions {
+            self.collide_balls();
+        }
 
-            self.players[1].points += 1;
-            println!(
-                "Player 2 scored, {}-{}",
-                self.players[0].points, self.players[1].points
-            );
-        } else if self.ball.position.x > self.size.width as f32 {
-            self.ball = Ball::new(
-                (self.size.width as f32 / 2.0) - (BALL_SIZE / 2.0),
-                (self.size.height as f32 / 2.0) - (BALL_SIZE / 2.0),
-                Vector2D::new(
-                    rand::distributions::Uniform::new(-1.0, 0.0).sample(&mut rand::thread_rng()),
-                    rand::distributions::Uniform::new(-0.1, 0.1).sample(&mut rand::thread_rng()),
-                ),
-                &self.size,
-            );
-            self.players[0].points += 1;
-            println!(
-                "Player 1 scored, {}-{}",
-                self.players[0].points, self.players[1].points
-            );
+        // A ball past a paddle scores for the player on the other side, or
+        // costs its player a life when playing for lives.
+        let mut index = 0;
+        while index < self.balls.len() {
+            let Some(player) = self.exited(&self.balls[index]) else {
+                index += 1;
+                continue;
+            };
+
+            self.balls.remove(index);
+            if self.rules.uses_lives() {
+                self.life_lost(player);
+            } else {
+                self.point_scored(1 - player);
+            }
+            if self.state != crate::rules::MatchState::Rally {
+                return;
+            }
         }
+
+        self.spawn_update(dt);
     }
 
-    pub fn input(&mut self, player: usize, dt: f32) {
-        match self.players[player].input {
-            crate::pong::Input::Up => {
-                self.players[player].move_position(
-                    self.players[player].position.x,
-                    self.players[player].position.y + (PADDLE_SPEED * dt),
-                    &self.size,
+    // The player whose side the ball has fully left the arena through.
+    fn exited(&self, ball: &Ball) -> Option<usize> {
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let side = if ball.position.x + ball.size < 0.0 {
+            Side::Left
+        } else if ball.position.x > width {
+            Side::Right
+        } else if ball.position.y + ball.size < 0.0 {
+            Side::Bottom
+        } else if ball.position.y > height {
+            Side::Top
+        } else {
+            return None;
+        };
+
+        self.side_player(side)
+    }
+
+    pub fn walled(&self, side: Side) -> bool {
+        self.side_player(side).is_none()
+    }
+
+    // The player defending a side, unless it is walled off.
+    fn side_player(&self, side: Side) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|player| player.side == side && !player.eliminated())
+    }
+
+    pub fn ball_update(&mut self, index: usize, dt: f32) {
+        let obstacles = self.obstacles();
+        self.keep_ball_in_bounds(index);
+        self.free_ball(index, &obstacles);
+        self.curve_ball(index, dt);
+
+        let walls = self.walls();
+        let mut remaining = dt;
+        for _ in 0..MAX_COLLISIONS_PER_STEP {
+            let ball = &self.balls[index];
+            let displacement = Vector2D::new(
+                ball.speed * remaining * ball.direction.x,
+                ball.speed * remaining * ball.direction.y,
+            );
+            let ball = ball.aabb();
+
+            let hit = walls
+                .iter()
+                .map(|wall| (Collider::Wall, *wall))
+                .chain(
+                    obstacles
+                        .iter()
+                        .enumerate()
+                        .map(|(index, obstacle)| (Collider::Obstacle(index), *obstacle)),
+                )
+                .chain(
+                    self.players
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, player)| !player.eliminated())
+                        .map(|(index, player)| (Collider::Player(index), player.aabb())),
+                )
+                .filter_map(|(collider, target)| {
+                    ball.sweep(&displacement, &target)
+                        .map(|hit| (collider, hit))
+                })
+                .min_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+            let ball = &mut self.balls[index];
+            let Some((collider, hit)) = hit else {
+                ball.move_position(
+                    ball.position.x + displacement.x,
+                    ball.position.y + displacement.y,
                 );
+                break;
+            };
+
+            ball.move_position(
+                ball.position.x + displacement.x * hit.time,
+                ball.position.y + displacement.y * hit.time,
+            );
+            remaining *= 1.0 - hit.time;
 
-                if self.players[player].position.y + PADDLE_HEIGHT > self.size.height as f32 {
-                    self.players[player].move_position(
-                        self.players[player].position.x,
-                        self.size.height as f32 - PADDLE_HEIGHT,
-                        &self.size,
-                    );
+            match collider {
+                Collider::Wall => {
+                    if hit.normal.x != 0.0 {
+                        ball.direction.x = hit.normal.x * ball.direction.x.abs();
+                    } else {
+                        ball.direction.y = hit.normal.y * ball.direction.y.abs();
+                    }
+                    self.spin_wall_bounce(index);
+                    self.emit(crate::event::Event::WallBounce {
+                        side: Side::facing(&hit.normal),
+                    });
                 }
+                Collider::Obstacle(obstacle) => {
+                    if hit.normal.x != 0.0 {
+                        ball.direction.x = hit.normal.x * ball.direction.x.abs();
+                    } else {
+                        ball.direction.y = hit.normal.y * ball.direction.y.abs();
+                    }
+                    self.spin_wall_bounce(index);
+                    self.emit(crate::event::Event::ObstacleBounce { obstacle });
+                }
+                Collider::Player(player) => self.paddle_bounce(index, player, &hit.normal),
             }
-            crate::pong::Input::Down => {
-                self.players[player].move_position(
-                    self.players[player].position.x,
-                    self.players[player].position.y - (PADDLE_SPEED * dt),
-                    &self.size,
+        }
+    }
+
+    fn paddle_bounce(&mut self, index: usize, player: usize, normal: &Vector2D) {
+        let paddle = &self.players[player];
+        let axis = paddle.side.axis();
+        let ball = &mut self.balls[index];
+        let distance = axis.split(ball.center()).1 - axis.split(paddle.center()).1;
+        let offset = distance / ((paddle.length() + ball.size) / 2.0);
+        ball.speed = (ball.speed * self.config.ball_speedup)
+            .min(self.config.ball_max_speed.max(self.config.ball_speed));
+        let speed = ball.speed;
+        self.rally_hits += 1;
+        self.emit(crate::event::Event::PaddleHit {
+            player,
+            offset,
+            speed,
+        });
+
+        let paddle = &self.players[player];
+        let ball = &mut self.balls[index];
+        let (normal, normal_across) = axis.split(*normal);
+        let (along, across) = axis.split(ball.direction);
+        if normal == 0.0 {
+            ball.direction = axis.join(along, normal_across * across.abs());
+            return;
+        }
+        ball.travel = axis;
+
+        if self.config.bounce == crate::config::BounceModel::Spin {
+            // The contact offset alone picks the angle, and the paddle's
+            // motion sets the spin.
+            let angle = offset.clamp(-1.0, 1.0) * self.config.max_bounce_angle.to_radians();
+            ball.direction = axis.join(normal * angle.cos(), angle.sin());
+            ball.spin = paddle.velocity * self.config.spin_transfer;
+            return;
+        }
+
+        let along = normal * along.abs();
+
+        let center = self.config.bounce_center * paddle.length() / 2.0;
+        let across = if (-center..=center).contains(&distance) {
+            rand::distributions::Uniform::new(-0.05, 0.05).sample(&mut self.rng)
+        } else if distance > center {
+            rand::distributions::Uniform::new(0.3, 1.0).sample(&mut self.rng)
+        } else {
+            rand::distributions::Uniform::new(-1.0, -0.3).sample(&mut self.rng)
+        };
+        ball.direction = axis.join(along, across).normalize();
+    }
+
+    fn curve_ball(&mut self, index: usize, dt: f32) {
+        let ball = &self.balls[index];
+        if ball.spin == 0.0 {
+            return;
+        }
+
+        let (along, across) = ball.travel.split(ball.direction);
+        let direction = ball.travel.join(along, across + ball.spin * dt);
+        let direction = self.limit_angle(direction.normalize(), ball.travel);
+        let ball = &mut self.balls[index];
+        ball.direction = direction;
+        ball.spin *= (1.0 - self.config.spin_decay * dt).max(0.0);
+    }
+
+    // A spinning ball skids off walls at a flatter angle, losing part of its
+    // spin and curving back away from the wall.
+    fn spin_wall_bounce(&mut self, index: usize) {
+        let ball = &self.balls[index];
+        if ball.spin == 0.0 {
+            return;
+        }
+
+        let grip = self.config.wall_grip;
+        let (along, across) = ball.travel.split(ball.direction);
+        let direction = ball
+            .travel
+            .join(along + along.signum() * ball.spin.abs() * grip, across);
+        let direction = self.limit_angle(direction.normalize(), ball.travel);
+        let ball = &mut self.balls[index];
+        ball.direction = direction;
+        ball.spin *= grip - 1.0;
+    }
+
+    // Keeps a unit direction within the max bounce angle of the axis,
+    // without changing which way it travels.
+    fn limit_angle(&self, direction: Vector2D, axis: Axis) -> Vector2D {
+        let max = self.config.max_bounce_angle.to_radians().sin();
+        let (along, across) = axis.split(direction);
+        let across = across.clamp(-max, max);
+        axis.join(along.signum() * (1.0 - across * across).sqrt(), across)
+    }
+
+    // Overlapping balls that move towards each other trade their velocities
+    // along the axis they overlap least on, as equal masses would.
+    fn collide_balls(&mut self) {
+        for first in 0..self.balls.len() {
+            for second in first + 1..self.balls.len() {
+                let (a, b) = (&self.balls[first], &self.balls[second]);
+                if !a.aabb().overlaps(&b.aabb()) {
+                    continue;
+                }
+
+                let (a_center, b_center) = (a.center(), b.center());
+                let overlap = Vector2D::new(
+                    (a.size + b.size) / 2.0 - (b_center.x - a_center.x).abs(),
+                    (a.size + b.size) / 2.0 - (b_center.y - a_center.y).abs(),
                 );
+                let mut a_velocity =
+                    Vector2D::new(a.direction.x * a.speed, a.direction.y * a.speed);
+                let mut b_velocity =
+                    Vector2D::new(b.direction.x * b.speed, b.direction.y * b.speed);
 
-                if self.players[player].position.y < 0.0 {
-                    self.players[player].move_position(
-                        self.players[player].position.x,
-                        0.0,
-                        &self.size,
-                    );
+                let (a_offset, b_offset) = if overlap.x < overlap.y {
+                    let side = (b_center.x - a_center.x).signum();
+                    if (a_velocity.x - b_velocity.x) * side <= 0.0 {
+                        continue;
+                    }
+                    std::mem::swap(&mut a_velocity.x, &mut b_velocity.x);
+                    (
+                        Vector2D::new(-side * overlap.x / 2.0, 0.0),
+                        Vector2D::new(side * overlap.x / 2.0, 0.0),
+                    )
+                } else {
+                    let side = (b_center.y - a_center.y).signum();
+                    if (a_velocity.y - b_velocity.y) * side <= 0.0 {
+                        continue;
+                    }
+                    std::mem::swap(&mut a_velocity.y, &mut b_velocity.y);
+                    (
+                        Vector2D::new(0.0, -side * overlap.y / 2.0),
+                        Vector2D::new(0.0, side * overlap.y / 2.0),
+                    )
+                };
+
+                for (index, velocity, offset) in [
+                    (first, a_velocity, a_offset),
+                    (second, b_velocity, b_offset),
+                ] {
+                    let speed = (velocity.x * velocity.x + velocity.y * velocity.y).sqrt();
+                    let direction =
+                        self.limit_angle(velocity.normalize(), self.balls[index].travel);
+                    let ball = &mut self.balls[index];
+                    ball.move_position(ball.position.x + offset.x, ball.position.y + offset.y);
+                    ball.direction = direction;
+                    ball.speed = speed;
                 }
+                self.emit(crate::event::Event::BallCollision);
+            }
+        }
+    }
+
+    // Puts another ball in play from the center once the rally has seen
+    // enough paddle hits or has run long enough.
+    fn spawn_update(&mut self, dt: f32) {
+        if self.balls.len() >= self.config.max_balls as usize {
+            return;
+        }
+
+        self.spawn_timer += dt;
+        let hits = self.config.multiball_hits;
+        let interval = self.config.multiball_interval;
+        if !((hits > 0 && self.rally_hits >= hits)
+            || (interval > 0.0 && self.spawn_timer >= interval))
+        {
+            return;
+        }
+
+        // Heads for the side of a random player still in.
+        let sides: Vec<Side> = self
+            .players
+            .iter()
+            .filter(|player| !player.eliminated())
+            .map(|player| player.side)
+            .collect();
+        let side = sides[self.rng.gen_range(0..sides.len() as u32) as usize];
+        let axis = side.axis();
+        let toward = -axis.split(side.normal()).0;
+        let mut ball = Controller::centered_ball(&self.size, &self.config);
+        ball.direction = axis
+            .join(
+                toward * rand::distributions::Uniform::new(0.5, 1.0).sample(&mut self.rng),
+                rand::distributions::Uniform::new(-0.5, 0.5).sample(&mut self.rng),
+            )
+            .normalize();
+        ball.travel = axis;
+        let direction = ball.direction;
+
+        self.balls.push(ball);
+        self.rally_hits = 0;
+        self.spawn_timer = 0.0;
+        self.emit(crate::event::Event::BallSpawned {
+            direction,
+            balls: self.balls.len(),
+        });
+    }
+
+    // Every side without a paddle in play is walled off, as are the parts
+    // of the others outside the level's goals.
+    fn walls(&self) -> Vec<crate::collision::Aabb> {
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+
+        let mut walls = Vec::new();
+        for side in [Side::Top, Side::Bottom, Side::Left, Side::Right] {
+            let length = side.axis().split(Vector2D::new(width, height)).1;
+            let stretches = if self.walled(side) {
+                vec![(0.0, length)]
+            } else {
+                self.level.blocked(side)
+            };
+
+            for (start, end) in stretches {
+                // Stretches reaching a corner carry on past it.
+                let start = if start <= 0.0 { -length } else { start };
+                let end = if end >= length { length * 2.0 } else { end };
+                walls.push(match side {
+                    Side::Top => crate::collision::Aabb::new(start, height, end - start, height),
+                    Side::Bottom => {
+                        crate::collision::Aabb::new(start, -height, end - start, height)
+                    }
+                    Side::Left => crate::collision::Aabb::new(-width, start, width, end - start),
+                    Side::Right => crate::collision::Aabb::new(width, start, width, end - start),
+                });
+            }
+        }
+        walls
+    }
+
+    // Moving obstacles can run into a ball, which is pushed out the short
+    // way and sent off in that direction.
+    fn free_ball(&mut self, index: usize, obstacles: &[crate::collision::Aabb]) {
+        for (obstacle, target) in obstacles.iter().enumerate() {
+            let ball = &mut self.balls[index];
+            if !ball.aabb().overlaps(target) {
+                continue;
+            }
+
+            let center = ball.center();
+            let offset = Vector2D::new(
+                center.x - (target.x + target.width / 2.0),
+                center.y - (target.y + target.height / 2.0),
+            );
+            let overlap = Vector2D::new(
+                (ball.size + target.width) / 2.0 - offset.x.abs(),
+                (ball.size + target.height) / 2.0 - offset.y.abs(),
+            );
+            let side = |offset: f32| if offset < 0.0 { -1.0 } else { 1.0 };
+            if overlap.x < overlap.y {
+                let side = side(offset.x);
+                ball.move_position(ball.position.x + side * overlap.x, ball.position.y);
+                ball.direction.x = side * ball.direction.x.abs();
+            } else {
+                let side = side(offset.y);
+                ball.move_position(ball.position.x, ball.position.y + side * overlap.y);
+                ball.direction.y = side * ball.direction.y.abs();
             }
-            crate::pong::Input::None => (),
+            self.emit(crate::event::Event::ObstacleBounce { obstacle });
+        }
+    }
+
+    fn keep_ball_in_bounds(&mut self, index: usize) {
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let [left, right, bottom, top] = Side::ALL.map(|side| self.walled(side));
+        let ball = &mut self.balls[index];
+
+        if bottom && ball.position.y < 0.0 {
+            ball.move_position(ball.position.x, 0.0);
+            ball.direction.y = ball.direction.y.abs();
+        } else if top && ball.position.y + ball.size > height {
+            ball.move_position(ball.position.x, height - ball.size);
+            ball.direction.y = -ball.direction.y.abs();
+        }
+
+        if left && ball.position.x < 0.0 {
+            ball.move_position(0.0, ball.position.y);
+            ball.direction.x = ball.direction.x.abs();
+        } else if right && ball.position.x + ball.size > width {
+            ball.move_position(width - ball.size, ball.position.y);
+            ball.direction.x = -ball.direction.x.abs();
         }
     }
+
+    pub fn input(&mut self, player: usize, dt: f32) {
+        let input = self.players[player].input;
+        let arena = Vector2D::new(self.size.width as f32, self.size.height as f32);
+        let player = &mut self.players[player];
+        player.velocity = 0.0;
+        if input == crate::pong::Input::None {
+            return;
+        }
+
+        // Paddles slide across the axis they send the ball along, so up
+        // moves paddles on the top and bottom to the right.
+        let axis = player.side.axis();
+        let (depth, position) = axis.split(player.position);
+        let length = player.length();
+        let top = axis.split(arena).1 - length;
+        let distance = match input {
+            crate::pong::Input::Target(target) => {
+                (target as f32 - length / 2.0).min(top).max(0.0) - position
+            }
+            crate::pong::Input::Axis(_) => self.config.paddle_max_speed * input.velocity() * dt,
+            _ => self.config.paddle_speed * input.velocity() * dt,
+        };
+
+        let max = self.config.paddle_max_speed * dt;
+        let moved = (position + distance.clamp(-max, max)).min(top).max(0.0);
+        if dt > 0.0 {
+            player.velocity = (moved - position) / dt;
+        }
+        player.position = axis.join(depth, moved);
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Input {
     Up,
     Down,
+    #[default]
     None,
+    // Share of the max paddle speed, scaled to -127..=127 so inputs stay
+    // exact across replays and the network. Positive moves up.
+    Axis(i8),
+    // Position along its side the paddle center moves towards, as fast as
+    // allowed.
+    Target(u16),
+}
+
+impl Input {
+    pub const AXIS_MAX: i8 = 127;
+
+    pub fn axis(value: f32) -> Self {
+        Input::Axis((value.clamp(-1.0, 1.0) * Input::AXIS_MAX as f32).round() as i8)
+    }
+
+    pub fn target(y: f32) -> Self {
+        Input::Target(y.round().clamp(0.0, u16::MAX as f32) as u16)
+    }
+
+    // Direction and share of the speed to move at. Targets pick their own
+    // direction and read as zero.
+    pub fn velocity(self) -> f32 {
+        match self {
+            Input::Up => 1.0,
+            Input::Down => -1.0,
+            Input::None | Input::Target(_) => 0.0,
+            Input::Axis(value) => value.max(-Input::AXIS_MAX) as f32 / Input::AXIS_MAX as f32,
+        }
+    }
+
+    pub fn to_bits(self) -> u8 {
+        match self {
+            Input::None => 0,
+            Input::Up => 1,
+            Input::Down => 2,
+            Input::Axis(_) => 3,
+            Input::Target(_) => 4,
+        }
+    }
+
+    // Analog inputs carry their value in bytes after the packed bits.
+    pub fn payload(self) -> Vec<u8> {
+        match self {
+            Input::Axis(value) => vec![value as u8],
+            Input::Target(y) => y.to_le_bytes().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn payload_len(bits: u8) -> usize {
+        match bits {
+            3 => 1,
+            4 => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn from_bits(bits: u8, payload: &[u8]) -> Option<Self> {
+        match (bits, payload) {
+            (0, []) => Some(Input::None),
+            (1, []) => Some(Input::Up),
+            (2, []) => Some(Input::Down),
+            (3, &[value]) => Some(Input::Axis(value as i8)),
+            (4, &[low, high]) => Some(Input::Target(u16::from_le_bytes([low, high]))),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(config: crate::config::GameConfig) -> Controller {
+        Controller::new(
+            &winit::dpi::PhysicalSize::new(800, 600),
+            1,
+            crate::rules::Ruleset::default(),
+            config,
+            crate::level::Level::default(),
+        )
+    }
+
+    // Puts a single ball in play with its center at `center`.
+    fn rally(controller: &mut Controller, center: Vector2D, direction: Vector2D, speed: f32) {
+        let size = controller.config.ball_size;
+        controller.balls = vec![Ball::new(
+            center.x - size / 2.0,
+            center.y - size / 2.0,
+            size,
+            direction,
+            speed,
+        )];
+        controller.state = crate::rules::MatchState::Rally;
+    }
+
+    fn spin_config() -> crate::config::GameConfig {
+        crate::config::GameConfig {
+            bounce: crate::config::BounceModel::Spin,
+            ..crate::config::GameConfig::default()
+        }
+    }
+
+    // Degrees away from straight along `axis`, positive going up or right.
+    fn angle(direction: Vector2D, axis: Axis) -> f32 {
+        let (along, across) = axis.split(direction);
+        across.atan2(along.abs()).to_degrees()
+    }
+
+    #[test]
+    fn fast_balls_do_not_tunnel_through_paddles() {
+        let mut controller = controller(crate::config::GameConfig {
+            ball_max_speed: 4000.0,
+            ..crate::config::GameConfig::default()
+        });
+        rally(
+            &mut controller,
+            Vector2D::new(400.0, 300.0),
+            Vector2D::new(-1.0, 0.0),
+            4000.0,
+        );
+
+        // Each tick moves the ball a third of the arena, many times the
+        // paddle's width.
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            controller.update(1.0 / 15.0);
+            events.extend_from_slice(controller.events());
+        }
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, crate::event::Event::PaddleHit { player: 0, .. })));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, crate::event::Event::PointScored { .. })));
+        assert!(controller.balls[0].direction.x > 0.0);
+    }
+
+    #[test]
+    fn spin_bounces_stay_within_the_max_angle() {
+        let mut controller = controller(spin_config());
+        let max = controller.config.max_bounce_angle;
+        let paddle = controller.players[0].center();
+        let reach = (controller.players[0].length() + controller.config.ball_size) / 2.0;
+
+        let mut last = -90.0;
+        for step in -10..=10 {
+            let offset = reach * step as f32 / 10.0;
+            rally(
+                &mut controller,
+                Vector2D::new(40.0, paddle.y + offset),
+                Vector2D::new(-1.0, 0.0),
+                400.0,
+            );
+            controller.paddle_bounce(0, 0, &Vector2D::new(1.0, 0.0));
+
+            let direction = controller.balls[0].direction;
+            let angle = angle(direction, Axis::Horizontal);
+            assert!(direction.x > 0.0);
+            assert!(angle.abs() <= max + 1e-3, "{} at offset {}", angle, offset);
+            assert!(angle > last);
+            last = angle;
+            match step {
+                -10 => assert!((angle + max).abs() < 1e-3),
+                0 => assert!(angle.abs() < 1e-3),
+                10 => assert!((angle - max).abs() < 1e-3),
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn spin_bounces_off_bottom_paddles_stay_within_the_max_angle() {
+        let mut controller = Controller::new(
+            &winit::dpi::PhysicalSize::new(800, 800),
+            1,
+            crate::rules::Ruleset {
+                players: 4,
+                ..crate::rules::Ruleset::default()
+            },
+            spin_config(),
+            crate::level::Level::default(),
+        );
+        let max = controller.config.max_bounce_angle;
+        let paddle = controller.players[2].center();
+        let reach = (controller.players[2].length() + controller.config.ball_size) / 2.0;
+
+        for (offset, expected) in [(-reach, -max), (0.0, 0.0), (reach, max)] {
+            rally(
+                &mut controller,
+                Vector2D::new(paddle.x + offset, 40.0),
+                Vector2D::new(0.0, -1.0),
+                400.0,
+            );
+            controller.paddle_bounce(0, 2, &Vector2D::new(0.0, 1.0));
+
+            let direction = controller.balls[0].direction;
+            assert!(direction.y > 0.0);
+            assert_eq!(controller.balls[0].travel, Axis::Vertical);
+            assert!((angle(direction, Axis::Vertical) - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn moving_paddles_spin_the_ball_their_way() {
+        let mut controller = controller(spin_config());
+        let paddle = controller.players[0].center();
+
+        for (velocity, spin) in [
+            (300.0, std::cmp::Ordering::Greater),
+            (-300.0, std::cmp::Ordering::Less),
+            (0.0, std::cmp::Ordering::Equal),
+        ] {
+            controller.players[0].velocity = velocity;
+            rally(
+                &mut controller,
+                Vector2D::new(40.0, paddle.y),
+                Vector2D::new(-1.0, 0.0),
+                400.0,
+            );
+            controller.paddle_bounce(0, 0, &Vector2D::new(1.0, 0.0));
+            assert_eq!(controller.balls[0].spin.partial_cmp(&0.0), Some(spin));
+        }
+    }
+
+    #[test]
+    fn curving_never_passes_the_max_angle() {
+        let mut controller = controller(spin_config());
+        let max = controller.config.max_bounce_angle;
+
+        for spin in [5.0, -5.0] {
+            rally(
+                &mut controller,
+                Vector2D::new(400.0, 300.0),
+                Vector2D::new(1.0, 0.0),
+                400.0,
+            );
+            controller.balls[0].spin = spin;
+            for _ in 0..240 {
+                controller.curve_ball(0, 1.0 / 120.0);
+                let direction = controller.balls[0].direction;
+                assert!(angle(direction, Axis::Horizontal).abs() <= max + 1e-3);
+                assert!(direction.x > 0.0);
+            }
+            // Enough spin to reach the limit, and held there.
+            let angle = angle(controller.balls[0].direction, Axis::Horizontal);
+            assert!((angle.abs() - max).abs() < 1e-3);
+            assert_eq!(angle.signum(), spin.signum());
+        }
+    }
+
+    #[test]
+    fn wall_bounces_with_spin_stay_within_the_max_angle() {
+        let mut controller = controller(spin_config());
+        let max = controller.config.max_bounce_angle;
+
+        let steep = max.to_radians();
+        rally(
+            &mut controller,
+            Vector2D::new(400.0, 300.0),
+            Vector2D::new(steep.cos(), steep.sin()),
+            400.0,
+        );
+        controller.balls[0].spin = -20.0;
+        controller.spin_wall_bounce(0);
+        assert!(angle(controller.balls[0].direction, Axis::Horizontal).abs() <= max + 1e-3);
+
+        // A spinning ball bouncing between the walls over a whole rally.
+        rally(
+            &mut controller,
+            Vector2D::new(400.0, 300.0),
+            Vector2D::new(0.3, 1.0),
+            600.0,
+        );
+        controller.balls[0].spin = 4.0;
+        let mut bounces = 0;
+        for _ in 0..600 {
+            controller.update(1.0 / 120.0);
+            bounces += controller
+                .events()
+                .iter()
+                .filter(|event| matches!(event, crate::event::Event::WallBounce { .. }))
+                .count();
+            let Some(ball) = controller.balls.first() else {
+                break;
+            };
+            assert!(angle(ball.direction, Axis::Horizontal).abs() <= max + 1e-3);
+        }
+        assert!(bounces > 0);
+    }
+
+    #[test]
+    fn paddles_longer_than_the_arena_stay_put() {
+        let mut controller = controller(crate::config::GameConfig {
+            paddle_height: 5000.0,
+            ..crate::config::GameConfig::default()
+        });
+
+        for input in [Input::Up, Input::Down, Input::Target(300)] {
+            controller.players[0].input = input;
+            controller.input(0, 1.0 / 60.0);
+            assert_eq!(controller.players[0].position.y, 0.0);
+        }
+    }
 }
@@ -0,0 +1,507 @@
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 8] = b"PONGRPLY";
+pub const VERSION: u16 = 1;
+// About ten hours at the default tick rate, which bounds what a small file
+// of long runs can expand to.
+pub const MAX_TICKS: u64 = 1 << 22;
+const MAX_LEVEL_ITEMS: u32 = 1 << 12;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Corrupt(&'static str),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "replay i/o error: {}", e),
+            ReplayError::BadMagic => write!(f, "not a pong replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay version {}", version)
+            }
+            ReplayError::Corrupt(reason) => write!(f, "corrupt replay: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickInput {
+    pub inputs: [crate::pong::Input; crate::rules::MAX_PLAYERS],
+    pub serve: bool,
+}
+
+impl TickInput {
+    // A packed little endian u16 of every input and the serve flag, followed
+    // by the payload of each analog input.
+    pub fn write(self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut packed = (self.serve as u16) << 12;
+        for (player, input) in self.inputs.iter().enumerate() {
+            packed |= (input.to_bits() as u16) << (player * 3);
+        }
+        writer.write_all(&packed.to_le_bytes())?;
+        for input in self.inputs {
+            writer.write_all(&input.payload())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self, ReplayError> {
+        let packed = u16::from_le_bytes(Replay::read_array(&mut reader)?);
+
+        let mut inputs = [crate::pong::Input::None; crate::rules::MAX_PLAYERS];
+        for (player, input) in inputs.iter_mut().enumerate() {
+            let bits = (packed >> (player * 3) & 0b111) as u8;
+            let mut payload = vec![0; crate::pong::Input::payload_len(bits)];
+            reader.read_exact(&mut payload)?;
+            *input = crate::pong::Input::from_bits(bits, &payload)
+                .ok_or(ReplayError::Corrupt("unknown input"))?;
+        }
+
+        Ok(Self {
+            inputs,
+            serve: packed & 1 << 12 != 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub seed: u64,
+    pub size: winit::dpi::PhysicalSize<u32>,
+    pub tick_rate: f32,
+    pub rules: crate::rules::Ruleset,
+    pub config: crate::config::GameConfig,
+    pub level: crate::level::Level,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub header: Header,
+    pub ticks: Vec<TickInput>,
+}
+
+impl Replay {
+    pub fn new(header: Header) -> Self {
+        Self {
+            header,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn record(controller: &crate::pong::Controller, tick_rate: f32) -> Self {
+        Replay::new(Header {
+            seed: controller.seed(),
+            size: controller.size(),
+            tick_rate,
+            rules: controller.rules().clone(),
+            config: controller.config().clone(),
+            level: controller.level().clone(),
+        })
+    }
+
+    pub fn controller(&self) -> crate::pong::Controller {
+        crate::pong::Controller::new(
+            &self.header.size,
+            self.header.seed,
+            self.header.rules.clone(),
+            self.header.config.clone(),
+            self.header.level.clone(),
+        )
+    }
+
+    // Layout: magic, version, header fields, then the tick inputs
+    // run-length encoded as (varint run length, tick input) pairs.
+    pub fn write(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let header = &self.header;
+        let rules = &header.rules;
+        let config = &header.config;
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&header.seed.to_le_bytes())?;
+        writer.write_all(&header.size.width.to_le_bytes())?;
+        writer.write_all(&header.size.height.to_le_bytes())?;
+        writer.write_all(&header.tick_rate.to_le_bytes())?;
+        writer.write_all(&rules.points_to_win.to_le_bytes())?;
+        writer.write_all(&[rules.win_by_two as u8])?;
+        writer.write_all(&rules.best_of.to_le_bytes())?;
+        writer.write_all(&[match rules.serve {
+            crate::rules::ServeRule::Alternate => 0,
+            crate::rules::ServeRule::Loser => 1,
+            crate::rules::ServeRule::Winner => 2,
+        }])?;
+        writer.write_all(&rules.point_delay.to_le_bytes())?;
+        writer.write_all(&[rules.players as u8])?;
+        writer.write_all(&rules.lives.to_le_bytes())?;
+        for value in [
+            config.paddle_width,
+            config.paddle_height,
+            config.paddle_speed,
+            config.paddle_max_speed,
+            config.ball_size,
+            config.ball_speed,
+            config.ball_speedup,
+            config.ball_max_speed,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&[match config.bounce {
+            crate::config::BounceModel::Zones => 0,
+            crate::config::BounceModel::Spin => 1,
+        }])?;
+        for value in [
+            config.bounce_center,
+            config.max_bounce_angle,
+            config.spin_transfer,
+            config.spin_decay,
+            config.wall_grip,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&config.max_balls.to_le_bytes())?;
+        writer.write_all(&config.multiball_hits.to_le_bytes())?;
+        writer.write_all(&config.multiball_interval.to_le_bytes())?;
+        writer.write_all(&[config.ball_collisions as u8])?;
+        Replay::write_level(&mut writer, &header.level)?;
+
+        let mut runs = Vec::new();
+        for tick in &self.ticks {
+            match runs.last_mut() {
+                Some((last, count)) if last == tick => *count += 1,
+                _ => runs.push((*tick, 1u64)),
+            }
+        }
+
+        Replay::write_varint(&mut writer, runs.len() as u64)?;
+        for (tick, count) in runs {
+            Replay::write_varint(&mut writer, count)?;
+            tick.write(&mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self, ReplayError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+
+        let version = u16::from_le_bytes(Replay::read_array(&mut reader)?);
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let seed = u64::from_le_bytes(Replay::read_array(&mut reader)?);
+        let width = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let height = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let tick_rate = f32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let points_to_win = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let [win_by_two] = Replay::read_array(&mut reader)?;
+        let best_of = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let serve = match Replay::read_array(&mut reader)? {
+            [0] => crate::rules::ServeRule::Alternate,
+            [1] => crate::rules::ServeRule::Loser,
+            [2] => crate::rules::ServeRule::Winner,
+            _ => return Err(ReplayError::Corrupt("unknown serve rule")),
+        };
+        let point_delay = f32::from_le_bytes(Replay::read_array(&mut reader)?);
+
+        let [players] = Replay::read_array(&mut reader)?;
+        let lives = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let rules = crate::rules::Ruleset {
+            points_to_win,
+            win_by_two: win_by_two != 0,
+            best_of,
+            serve,
+            point_delay,
+            players: players as usize,
+            lives,
+        };
+
+        let mut config = crate::config::GameConfig::default();
+        for value in [
+            &mut config.paddle_width,
+            &mut config.paddle_height,
+            &mut config.paddle_speed,
+            &mut config.paddle_max_speed,
+            &mut config.ball_size,
+            &mut config.ball_speed,
+            &mut config.ball_speedup,
+            &mut config.ball_max_speed,
+        ] {
+            *value = f32::from_le_bytes(Replay::read_array(&mut reader)?);
+        }
+        config.bounce = match Replay::read_array(&mut reader)? {
+            [0] => crate::config::BounceModel::Zones,
+            [1] => crate::config::BounceModel::Spin,
+            _ => return Err(ReplayError::Corrupt("unknown bounce model")),
+        };
+        for value in [
+            &mut config.bounce_center,
+            &mut config.max_bounce_angle,
+            &mut config.spin_transfer,
+            &mut config.spin_decay,
+            &mut config.wall_grip,
+        ] {
+            *value = f32::from_le_bytes(Replay::read_array(&mut reader)?);
+        }
+        config.max_balls = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        config.multiball_hits = u32::from_le_bytes(Replay::read_array(&mut reader)?);
+        config.multiball_interval = f32::from_le_bytes(Replay::read_array(&mut reader)?);
+        let [ball_collisions] = Replay::read_array(&mut reader)?;
+        config.ball_collisions = ball_collisions != 0;
+
+        let level = Replay::read_level(&mut reader, width, height)?;
+
+        if width == 0
+            || height == 0
+            || !(tick_rate > 0.0 && tick_rate.is_finite())
+            || best_of == 0
+            || points_to_win == 0
+            || !(2..=crate::rules::MAX_PLAYERS).contains(&rules.players)
+            || rules.lives == 0
+        {
+            return Err(ReplayError::Corrupt("invalid header"));
+        }
+        let size = winit::dpi::PhysicalSize { width, height };
+        if config.validate().is_err() || config.validate_arena(&size, rules.players).is_err() {
+            return Err(ReplayError::Corrupt("invalid game config"));
+        }
+        if level.validate(&config, rules.players).is_err() {
+            return Err(ReplayError::Corrupt("invalid level"));
+        }
+
+        let mut ticks = Vec::new();
+        for _ in 0..Replay::read_varint(&mut reader)? {
+            let count = Replay::read_varint(&mut reader)?;
+            let tick = TickInput::read(&mut reader)?;
+            if ticks.len() as u64 + count > MAX_TICKS {
+                return Err(ReplayError::Corrupt("too many ticks"));
+            }
+            ticks.extend(std::iter::repeat_n(tick, count as usize));
+        }
+
+        Ok(Self {
+            header: Header {
+                seed,
+                size,
+                tick_rate,
+                rules,
+                config,
+                level,
+            },
+            ticks,
+        })
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        crate::file::save(path, |writer| self.write(writer))
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, ReplayError> {
+        Replay::read(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    pub fn default_path() -> std::path::PathBuf {
+        crate::file::timestamped_path("replays", "replay")
+    }
+
+    // The name, obstacles as their rectangle, speed and path, then goals as
+    // their side and stretch. The arena size is already in the header.
+    fn write_level(writer: &mut impl Write, level: &crate::level::Level) -> std::io::Result<()> {
+        writer.write_all(&(level.name.len() as u32).to_le_bytes())?;
+        writer.write_all(level.name.as_bytes())?;
+        writer.write_all(&(level.obstacles.len() as u32).to_le_bytes())?;
+        for obstacle in &level.obstacles {
+            for value in [
+                obstacle.x,
+                obstacle.y,
+                obstacle.width,
+                obstacle.height,
+                obstacle.speed,
+            ] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&(obstacle.path.len() as u32).to_le_bytes())?;
+            for value in obstacle.path.iter().flatten() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&(level.goals.len() as u32).to_le_bytes())?;
+        for goal in &level.goals {
+            writer.write_all(&[goal.side as u8])?;
+            writer.write_all(&goal.start.to_le_bytes())?;
+            writer.write_all(&goal.end.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_level(
+        reader: &mut impl Read,
+        width: u32,
+        height: u32,
+    ) -> Result<crate::level::Level, ReplayError> {
+        let mut name = vec![0; Replay::read_count(reader)? as usize];
+        reader.read_exact(&mut name)?;
+        let mut level = crate::level::Level {
+            name: String::from_utf8(name)
+                .map_err(|_| ReplayError::Corrupt("invalid level name"))?,
+            width,
+            height,
+            ..crate::level::Level::default()
+        };
+
+        for _ in 0..Replay::read_count(reader)? {
+            let mut values = [0.0; 5];
+            for value in &mut values {
+                *value = f32::from_le_bytes(Replay::read_array(reader)?);
+            }
+            let mut path = Vec::new();
+            for _ in 0..Replay::read_count(reader)? {
+                path.push([
+                    f32::from_le_bytes(Replay::read_array(reader)?),
+                    f32::from_le_bytes(Replay::read_array(reader)?),
+                ]);
+            }
+            let [x, y, width, height, speed] = values;
+            level.obstacles.push(crate::level::Obstacle {
+                x,
+                y,
+                width,
+                height,
+                path,
+                speed,
+            });
+        }
+
+        for _ in 0..Replay::read_count(reader)? {
+            let [side] = Replay::read_array(reader)?;
+            let side = *crate::pong::Side::ALL
+                .get(side as usize)
+                .ok_or(ReplayError::Corrupt("unknown goal side"))?;
+            level.goals.push(crate::level::Goal {
+                side,
+                start: f32::from_le_bytes(Replay::read_array(reader)?),
+                end: f32::from_le_bytes(Replay::read_array(reader)?),
+            });
+        }
+
+        Ok(level)
+    }
+
+    fn read_count(reader: &mut impl Read) -> Result<u32, ReplayError> {
+        let count = u32::from_le_bytes(Replay::read_array(reader)?);
+        if count > MAX_LEVEL_ITEMS {
+            return Err(ReplayError::Corrupt("too many level items"));
+        }
+        Ok(count)
+    }
+
+    fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], ReplayError> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn write_varint(writer: &mut impl Write, mut value: u64) -> std::io::Result<()> {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                return writer.write_all(&[byte]);
+            }
+            writer.write_all(&[byte | 0x80])?;
+        }
+    }
+
+    fn read_varint(reader: &mut impl Read) -> Result<u64, ReplayError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let [byte] = Replay::read_array(reader)?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(ReplayError::Corrupt("varint too long"))
+    }
+}
+
+#[derive(Debug)]
+pub struct Playback {
+    replay: Replay,
+    position: usize,
+    pub paused: bool,
+    pub speed: f32,
+    step_requested: bool,
+}
+
+impl Playback {
+    pub const MIN_SPEED: f32 = 0.25;
+    pub const MAX_SPEED: f32 = 8.0;
+
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            position: 0,
+            paused: false,
+            speed: 1.0,
+            step_requested: false,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position >= self.replay.ticks.len()
+    }
+
+    pub fn next_tick(&mut self) -> Option<TickInput> {
+        let tick = self.replay.ticks.get(self.position).copied()?;
+        self.position += 1;
+        Some(tick)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    pub fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.step_requested)
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(Playback::MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(Playback::MIN_SPEED);
+    }
+}
@@ -0,0 +1,84 @@
+pub const MAX_PLAYERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServeRule {
+    Alternate,
+    Loser,
+    Winner,
+}
+
+impl std::str::FromStr for ServeRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alternate" => Ok(ServeRule::Alternate),
+            "loser" => Ok(ServeRule::Loser),
+            "winner" => Ok(ServeRule::Winner),
+            _ => Err(format!(
+                "unknown serve rule {}, expected alternate, loser or winner",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Ruleset {
+    pub points_to_win: u32,
+    pub win_by_two: bool,
+    pub best_of: u32,
+    pub serve: ServeRule,
+    pub point_delay: f32,
+    // With more than two players the top and bottom get paddles too, and
+    // players are knocked out after losing all their lives instead of
+    // playing for points.
+    pub players: usize,
+    pub lives: u32,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            points_to_win: 11,
+            win_by_two: true,
+            best_of: 1,
+            serve: ServeRule::Loser,
+            point_delay: 1.0,
+            players: 2,
+            lives: 3,
+        }
+    }
+}
+
+impl Ruleset {
+    pub fn uses_lives(&self) -> bool {
+        self.players > 2
+    }
+
+    pub fn sets_to_win(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    pub fn set_won(&self, points: u32, opponent_points: u32) -> bool {
+        points >= self.points_to_win && (!self.win_by_two || points >= opponent_points + 2)
+    }
+
+    pub fn next_server(&self, server: usize, scorer: usize) -> usize {
+        match self.serve {
+            ServeRule::Alternate => 1 - server,
+            ServeRule::Loser => 1 - scorer,
+            ServeRule::Winner => scorer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MatchState {
+    Serving { server: usize },
+    Rally,
+    PointScored { scorer: usize, remaining: f32 },
+    LifeLost { player: usize, remaining: f32 },
+    GameOver { winner: usize },
+}
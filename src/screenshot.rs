@@ -0,0 +1,57 @@
+#[derive(Debug)]
+pub enum ScreenshotError {
+    Map(wgpu::BufferAsyncError),
+    Io(std::io::Error),
+    Png(png::EncodingError),
+}
+
+impl std::fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScreenshotError::Map(e) => write!(f, "failed to read back frame: {}", e),
+            ScreenshotError::Io(e) => write!(f, "failed to write screenshot: {}", e),
+            ScreenshotError::Png(e) => write!(f, "failed to encode screenshot: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+impl From<std::io::Error> for ScreenshotError {
+    fn from(e: std::io::Error) -> Self {
+        ScreenshotError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(e: png::EncodingError) -> Self {
+        ScreenshotError::Png(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    pub fn save_png(&self, path: &std::path::Path) -> Result<(), ScreenshotError> {
+        crate::file::save(path, |file| {
+            let mut encoder = png::Encoder::new(file, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.rgba)?;
+            writer.finish()?;
+            Ok(())
+        })
+    }
+
+    pub fn default_path() -> std::path::PathBuf {
+        crate::file::timestamped_path("screenshots", "png")
+    }
+}
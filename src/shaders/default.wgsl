@@ -1,5 +1,13 @@
 // Vertex shader
 
+struct Transform {
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> transform: Transform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
@@ -16,7 +24,11 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.clip_position = vec4<f32>(
+        model.position.xy * transform.scale + transform.offset,
+        model.position.z,
+        1.0,
+    );
     return out;
 }
 
@@ -24,4 +36,4 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(in.color, 1.0);
-}
\ No newline at end of file
+}
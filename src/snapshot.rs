@@ -0,0 +1,357 @@
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 8] = b"PONGSNAP";
+pub const VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    UnsupportedVersion(u16),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot i/o error: {}", e),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Json(e) => write!(f, "invalid snapshot json: {}", e),
+            SnapshotError::Binary(e) => write!(f, "corrupt snapshot: {}", e),
+            SnapshotError::Invalid(reason) => write!(f, "invalid snapshot: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::Json(e)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(e: bincode::Error) -> Self {
+        SnapshotError::Binary(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Json,
+}
+
+impl Format {
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Binary,
+        }
+    }
+}
+
+// ChaCha8Rng is fully described by its seed, stream and position in that
+// stream, which keeps the saved form readable in json.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+mod rng_state {
+    use rand::SeedableRng;
+
+    pub fn serialize<S: serde::Serializer>(
+        rng: &rand_chacha::ChaCha8Rng,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(
+            &super::RngState {
+                seed: rng.get_seed(),
+                stream: rng.get_stream(),
+                word_pos: rng.get_word_pos(),
+            },
+            serializer,
+        )
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<rand_chacha::ChaCha8Rng, D::Error> {
+        let state: super::RngState = serde::Deserialize::deserialize(deserializer)?;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+        Ok(rng)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Version {
+    version: u16,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Versioned<T> {
+    version: u16,
+    snapshot: T,
+}
+
+// 64 bit FNV-1a, spelled out because std's hashers are free to change
+// between releases and peers may not share one.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: impl AsRef<[u8]>) {
+        for byte in bytes.as_ref() {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    pub size: winit::dpi::PhysicalSize<u32>,
+    pub seed: u64,
+    #[serde(with = "rng_state")]
+    pub rng: rand_chacha::ChaCha8Rng,
+    pub rules: crate::rules::Ruleset,
+    pub config: crate::config::GameConfig,
+    pub level: crate::level::Level,
+    pub level_time: f32,
+    pub state: crate::rules::MatchState,
+    pub server: usize,
+    pub serve_requested: bool,
+    pub rally_hits: u32,
+    pub spawn_timer: f32,
+    pub players: Vec<crate::pong::Player>,
+    pub balls: Vec<crate::pong::Ball>,
+}
+
+impl Snapshot {
+    // Hashes everything that affects future simulation so two peers can
+    // compare their states without exchanging them. Every value is fed in as
+    // fixed width little endian bytes so all builds and platforms agree.
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv1a::default();
+
+        hash.write(self.seed.to_le_bytes());
+        hash.write(self.size.width.to_le_bytes());
+        hash.write(self.size.height.to_le_bytes());
+        hash.write(self.rules.points_to_win.to_le_bytes());
+        hash.write([self.rules.win_by_two as u8]);
+        hash.write(self.rules.best_of.to_le_bytes());
+        hash.write([self.rules.serve as u8]);
+        hash.write(self.rules.point_delay.to_le_bytes());
+        hash.write((self.rules.players as u32).to_le_bytes());
+        hash.write(self.rules.lives.to_le_bytes());
+        for value in [
+            self.config.paddle_width,
+            self.config.paddle_height,
+            self.config.paddle_speed,
+            self.config.paddle_max_speed,
+            self.config.ball_size,
+            self.config.ball_speed,
+            self.config.ball_speedup,
+            self.config.ball_max_speed,
+            self.config.multiball_interval,
+            self.config.bounce_center,
+            self.config.max_bounce_angle,
+            self.config.spin_transfer,
+            self.config.spin_decay,
+            self.config.wall_grip,
+        ] {
+            hash.write(value.to_le_bytes());
+        }
+        hash.write([self.config.bounce as u8]);
+        hash.write(self.config.max_balls.to_le_bytes());
+        hash.write(self.config.multiball_hits.to_le_bytes());
+        hash.write([self.config.ball_collisions as u8]);
+        hash.write(self.level.width.to_le_bytes());
+        hash.write(self.level.height.to_le_bytes());
+        hash.write((self.level.obstacles.len() as u32).to_le_bytes());
+        for obstacle in &self.level.obstacles {
+            hash.write((obstacle.path.len() as u32).to_le_bytes());
+            for value in [
+                obstacle.x,
+                obstacle.y,
+                obstacle.width,
+                obstacle.height,
+                obstacle.speed,
+            ]
+            .into_iter()
+            .chain(obstacle.path.iter().flatten().copied())
+            {
+                hash.write(value.to_le_bytes());
+            }
+        }
+        hash.write((self.level.goals.len() as u32).to_le_bytes());
+        for goal in &self.level.goals {
+            hash.write([goal.side as u8]);
+            hash.write(goal.start.to_le_bytes());
+            hash.write(goal.end.to_le_bytes());
+        }
+        hash.write(self.level_time.to_le_bytes());
+        hash.write(self.rng.get_word_pos().to_le_bytes());
+        hash.write((self.server as u32).to_le_bytes());
+        hash.write([self.serve_requested as u8]);
+        hash.write(self.rally_hits.to_le_bytes());
+        hash.write(self.spawn_timer.to_le_bytes());
+        let (tag, index, remaining) = match self.state {
+            crate::rules::MatchState::Serving { server } => (0, server, 0.0),
+            crate::rules::MatchState::Rally => (1, 0, 0.0),
+            crate::rules::MatchState::PointScored { scorer, remaining } => (2, scorer, remaining),
+            crate::rules::MatchState::GameOver { winner } => (3, winner, 0.0),
+            crate::rules::MatchState::LifeLost { player, remaining } => (4, player, remaining),
+        };
+        hash.write([tag]);
+        hash.write((index as u32).to_le_bytes());
+        hash.write(remaining.to_le_bytes());
+        for player in &self.players {
+            hash.write(player.position.x.to_le_bytes());
+            hash.write(player.position.y.to_le_bytes());
+            hash.write(player.velocity.to_le_bytes());
+            hash.write(player.points.to_le_bytes());
+            hash.write(player.sets.to_le_bytes());
+            hash.write(player.lives.to_le_bytes());
+        }
+        hash.write((self.balls.len() as u32).to_le_bytes());
+        for ball in &self.balls {
+            hash.write(ball.position.x.to_le_bytes());
+            hash.write(ball.position.y.to_le_bytes());
+            hash.write(ball.direction.x.to_le_bytes());
+            hash.write(ball.direction.y.to_le_bytes());
+            hash.write(ball.speed.to_le_bytes());
+            hash.write(ball.spin.to_le_bytes());
+            hash.write([ball.travel as u8]);
+        }
+
+        hash.0
+    }
+
+    pub fn write(&self, mut writer: impl Write, format: Format) -> Result<(), SnapshotError> {
+        let versioned = Versioned {
+            version: VERSION,
+            snapshot: self,
+        };
+
+        match format {
+            Format::Binary => {
+                writer.write_all(MAGIC)?;
+                bincode::serialize_into(&mut writer, &versioned)?;
+            }
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut writer, &versioned)?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Binary snapshots are recognized by their magic, anything else is read
+    // as json.
+    pub fn read(mut reader: impl Read) -> Result<Self, SnapshotError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        if let Some(payload) = bytes.strip_prefix(MAGIC) {
+            let version: u16 = bincode::deserialize(payload)?;
+            if version != VERSION {
+                return Err(SnapshotError::UnsupportedVersion(version));
+            }
+            let versioned: Versioned<Snapshot> = bincode::deserialize(payload)?;
+            versioned.snapshot.validate()?;
+            Ok(versioned.snapshot)
+        } else {
+            let Version { version } = serde_json::from_slice(&bytes)?;
+            if version != VERSION {
+                return Err(SnapshotError::UnsupportedVersion(version));
+            }
+            let versioned: Versioned<Snapshot> = serde_json::from_slice(&bytes)?;
+            versioned.snapshot.validate()?;
+            Ok(versioned.snapshot)
+        }
+    }
+
+    // Snapshots can be edited by hand, so they are held to the same limits
+    // as the command line and config files before anything is restored.
+    pub fn validate(&self) -> Result<(), SnapshotError> {
+        let invalid = |reason: String| Err(SnapshotError::Invalid(reason));
+        let players = self.rules.players;
+
+        if !(2..=crate::rules::MAX_PLAYERS).contains(&players) {
+            return invalid(format!(
+                "rules.players must be between 2 and {}",
+                crate::rules::MAX_PLAYERS
+            ));
+        }
+        if self.rules.points_to_win == 0 || self.rules.best_of == 0 || self.rules.lives == 0 {
+            return invalid(
+                "rules.points_to_win, rules.best_of and rules.lives must be at least 1".to_string(),
+            );
+        }
+        self.config
+            .validate()
+            .and_then(|_| self.config.validate_arena(&self.size, players))
+            .map_err(|e| SnapshotError::Invalid(e.to_string()))?;
+        if (self.level.width, self.level.height) != (self.size.width, self.size.height) {
+            return invalid("the level does not match the arena size".to_string());
+        }
+        self.level
+            .validate(&self.config, players)
+            .map_err(|e| SnapshotError::Invalid(e.to_string()))?;
+
+        if self.players.len() != players {
+            return invalid(format!(
+                "{} players saved for a {} player match",
+                self.players.len(),
+                players
+            ));
+        }
+        let player = match self.state {
+            crate::rules::MatchState::Serving { server } => Some(server),
+            crate::rules::MatchState::PointScored { scorer, .. } => Some(scorer),
+            crate::rules::MatchState::LifeLost { player, .. } => Some(player),
+            crate::rules::MatchState::GameOver { winner } => Some(winner),
+            crate::rules::MatchState::Rally => None,
+        };
+        if player.is_some_and(|player| player >= players) || self.server >= players {
+            return invalid("state refers to a player that is not in the match".to_string());
+        }
+        if self.state == crate::rules::MatchState::Rally && self.balls.is_empty() {
+            return invalid("a rally needs at least one ball".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), SnapshotError> {
+        crate::file::save(path, |writer| self.write(writer, Format::from_path(path)))
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, SnapshotError> {
+        Snapshot::read(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    pub fn default_path() -> std::path::PathBuf {
+        crate::file::timestamped_path("snapshots", "json")
+    }
+}
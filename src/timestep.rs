@@ -0,0 +1,45 @@
+pub const DEFAULT_TICK_RATE: f32 = 120.0;
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[derive(Debug)]
+pub struct FixedTimestep {
+    rate: f32,
+    tick: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> Self {
+        Self {
+            rate: tick_rate,
+            tick: 1.0 / tick_rate,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn tick(&self) -> f32 {
+        self.tick
+    }
+
+    // Returns how many fixed ticks fit in the elapsed time. Long hitches are
+    // capped so the simulation never has to catch up more than MAX_FRAME_TIME.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.min(MAX_FRAME_TIME);
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+
+        ticks
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.tick
+    }
+}
@@ -0,0 +1,63 @@
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Transform {
+    pub scale: [f32; 2],
+    pub offset: [f32; 2],
+}
+
+// Fits the logical arena into the surface at the largest scale that keeps its
+// aspect ratio, centering it between letterbox bars.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub arena: winit::dpi::PhysicalSize<u32>,
+    pub surface: winit::dpi::PhysicalSize<u32>,
+}
+
+impl Viewport {
+    pub fn new(
+        arena: winit::dpi::PhysicalSize<u32>,
+        surface: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        Self { arena, surface }
+    }
+
+    // Surface pixels per arena unit.
+    pub fn scale(&self) -> f32 {
+        (self.surface.width as f32 / self.arena.width as f32)
+            .min(self.surface.height as f32 / self.arena.height as f32)
+    }
+
+    // The area the arena covers, in surface pixels from the bottom left.
+    pub fn rect(&self) -> crate::collision::Aabb {
+        let scale = self.scale();
+        let width = self.arena.width as f32 * scale;
+        let height = self.arena.height as f32 * scale;
+        crate::collision::Aabb::new(
+            (self.surface.width as f32 - width) / 2.0,
+            (self.surface.height as f32 - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    pub fn transform(&self) -> Transform {
+        let scale = self.scale();
+        let rect = self.rect();
+        let (width, height) = (self.surface.width as f32, self.surface.height as f32);
+
+        Transform {
+            scale: [2.0 * scale / width, 2.0 * scale / height],
+            offset: [2.0 * rect.x / width - 1.0, 2.0 * rect.y / height - 1.0],
+        }
+    }
+
+    // Maps a window position, which winit measures from the top left, to
+    // arena units. Points on the letterbox bars fall outside the arena.
+    pub fn to_arena(&self, position: winit::dpi::PhysicalPosition<f64>) -> crate::pong::Vector2D {
+        let scale = self.scale();
+        let rect = self.rect();
+        let y = self.surface.height as f32 - position.y as f32;
+
+        crate::pong::Vector2D::new((position.x as f32 - rect.x) / scale, (y - rect.y) / scale)
+    }
+}
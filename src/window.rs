@@ -1,21 +1,73 @@
 use winit::platform::pump_events::EventLoopExtPumpEvents;
 
-pub struct Window {}
+pub struct Window {
+    options: crate::engine::Options,
+    transport: Option<Box<dyn crate::net::Transport>>,
+}
 
 impl Window {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(
+        options: crate::engine::Options,
+        transport: Option<Box<dyn crate::net::Transport>>,
+    ) -> Self {
+        Self { options, transport }
+    }
+
+    fn fullscreen(
+        mode: crate::config::WindowMode,
+        window: &winit::window::Window,
+    ) -> Option<winit::window::Fullscreen> {
+        match mode {
+            crate::config::WindowMode::Windowed => None,
+            crate::config::WindowMode::Borderless => {
+                Some(winit::window::Fullscreen::Borderless(None))
+            }
+            crate::config::WindowMode::Exclusive => {
+                // Largest resolution of the current monitor at its highest
+                // refresh rate, or borderless when modes cannot be listed.
+                let video_mode = window.current_monitor().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|mode| {
+                        let size = mode.size();
+                        (size.width * size.height, mode.refresh_rate_millihertz())
+                    })
+                });
+                Some(match video_mode {
+                    Some(video_mode) => winit::window::Fullscreen::Exclusive(video_mode),
+                    None => winit::window::Fullscreen::Borderless(None),
+                })
+            }
+        }
     }
 
     pub fn run(&mut self) {
         let mut event_loop = winit::event_loop::EventLoop::new().unwrap();
+        let arena = self
+            .options
+            .replay
+            .as_ref()
+            .map_or(self.options.arena, |replay| replay.header.size);
         let window = winit::window::WindowBuilder::new()
             .with_title("pong")
-            .with_resizable(false)
+            .with_inner_size(winit::dpi::LogicalSize::new(arena.width, arena.height))
+            .with_resizable(true)
             .build(&event_loop)
             .unwrap();
+        window.set_fullscreen(Window::fullscreen(self.options.display.mode, &window));
 
-        let mut engine = futures::executor::block_on(crate::engine::Engine::new(&window));
+        // Alt+Enter switches between a window and the configured fullscreen
+        // mode, or borderless when the game was started windowed.
+        let fullscreen_mode = match self.options.display.mode {
+            crate::config::WindowMode::Windowed => crate::config::WindowMode::Borderless,
+            mode => mode,
+        };
+        let mut modifiers = winit::keyboard::ModifiersState::empty();
+
+        let mut engine =
+            futures::executor::block_on(crate::engine::Engine::new(&window, &self.options));
+        if let (Some(transport), Some(net)) = (self.transport.take(), &self.options.net) {
+            engine.connect(transport, net);
+        }
+        engine.gamepads = crate::gamepad::backend();
 
         let mut last_update = std::time::Instant::now();
 
@@ -43,7 +95,7 @@ impl Window {
                             let new_inner_size @ winit::dpi::PhysicalSize { width, height } =
                                 winit::dpi::PhysicalSize {
                                     width: (engine.config.width as f64 * size) as u32,
-                                    height: (engine.config.width as f64 * size) as u32,
+                                    height: (engine.config.height as f64 * size) as u32,
                                 };
                             if width > 0 && height > 0 {
                                 engine.resize(new_inner_size);
@@ -52,6 +104,9 @@ impl Window {
                         winit::event::WindowEvent::CloseRequested => {
                             event_loop.exit();
                         }
+                        winit::event::WindowEvent::ModifiersChanged(state) => {
+                            modifiers = state.state();
+                        }
                         winit::event::WindowEvent::KeyboardInput {
                             event:
                                 winit::event::KeyEvent {
@@ -61,13 +116,27 @@ impl Window {
                                 },
                             ..
                         } => {
-                            if let (
-                                winit::keyboard::KeyCode::Escape,
-                                winit::event::ElementState::Released,
-                            ) = (key, state)
+                            if *state == winit::event::ElementState::Released
+                                && self
+                                    .options
+                                    .bindings
+                                    .is_bound(*key, crate::bindings::Action::Quit)
                             {
                                 event_loop.exit();
                             }
+
+                            if let (
+                                winit::keyboard::KeyCode::Enter,
+                                winit::event::ElementState::Pressed,
+                                true,
+                            ) = (key, state, modifiers.alt_key())
+                            {
+                                let mode = match window.fullscreen() {
+                                    Some(_) => crate::config::WindowMode::Windowed,
+                                    None => fullscreen_mode,
+                                };
+                                window.set_fullscreen(Window::fullscreen(mode, &window));
+                            }
                         }
                         winit::event::WindowEvent::RedrawRequested => {
                             let now = std::time::Instant::now();
@@ -75,6 +144,7 @@ impl Window {
                             last_update = now;
 
                             engine.update(&dt);
+
                             match engine.render() {
                                 Ok(_) => {}
                                 Err(wgpu::SurfaceError::Lost) => {